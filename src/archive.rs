@@ -0,0 +1,428 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! block archives written by `thegarii export`
+//!
+//! an archive is a directory of files of consecutive firehose blocks, named
+//! after the first and last heights they hold, i.e.
+//! `000000000100-000000000199.jsonl.gz`. files are written as `.partial`
+//! and renamed once complete, so interrupted exports resume after the last
+//! complete file.
+use crate::{network::Network, pb, types::FirehoseBlock, Error, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression as GzLevel};
+use prost::Message;
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// extension of files being written
+const PARTIAL: &str = "partial";
+
+/// encoding of the blocks of an archive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `FirehoseBlock` JSON, one per line
+    Jsonl,
+    /// length-delimited protobuf `pb::Block`s
+    PbDelimited,
+}
+
+impl Format {
+    /// file extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::PbDelimited => "pb",
+        }
+    }
+
+    /// encoded `block` of `network`
+    pub fn encode(&self, block: FirehoseBlock, network: &Network) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Jsonl => {
+                let mut bytes = serde_json::to_vec(&block)?;
+                bytes.push(b'\n');
+                bytes
+            }
+            Self::PbDelimited => pb::Block::new(block, network).encode_length_delimited_to_vec(),
+        })
+    }
+
+    /// next block of `network` encoded in `reader`, `None` at the end of it
+    fn decode(
+        &self,
+        reader: &mut impl BufRead,
+        network: &Network,
+    ) -> Result<Option<FirehoseBlock>> {
+        match self {
+            Self::Jsonl => {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(serde_json::from_str(&line)?))
+            }
+            Self::PbDelimited => {
+                let len = match read_varint(reader)? {
+                    Some(len) => len,
+                    None => return Ok(None),
+                };
+                let mut bytes = vec![0; len as usize];
+                reader.read_exact(&mut bytes)?;
+                let block = pb::Block::decode(&bytes[..])
+                    .map_err(|e| Error::InvalidPayload(e.to_string()))?;
+                Ok(Some(block.into_firehose(network)?))
+            }
+        }
+    }
+}
+
+/// length delimiter of a protobuf message, `None` at the end of `reader`
+fn read_varint(reader: &mut impl Read) -> Result<Option<u64>> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if let Err(e) = reader.read_exact(&mut byte) {
+            return match e.kind() {
+                ErrorKind::UnexpectedEof if shift == 0 => Ok(None),
+                _ => Err(e.into()),
+            };
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] < 0x80 {
+            return Ok(Some(value));
+        }
+    }
+
+    Err(Error::InvalidPayload("invalid length delimiter".into()))
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "pb-delimited" => Ok(Self::PbDelimited),
+            _ => Err(anyhow::anyhow!("invalid format {}, expect jsonl or pb-delimited", s).into()),
+        }
+    }
+}
+
+/// compression of the files of an archive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// file extension, if compressed
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => {
+                Err(anyhow::anyhow!("invalid compression {}, expect none, gzip or zstd", s).into())
+            }
+        }
+    }
+}
+
+/// complete file of an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveFile {
+    /// height of the first block
+    pub first: u64,
+    /// height of the last block
+    pub last: u64,
+    pub path: PathBuf,
+}
+
+/// suffix of the files in `format` and `compression`
+fn suffix(format: Format, compression: Compression) -> String {
+    match compression.extension() {
+        Some(compressed) => format!(".{}.{}", format.extension(), compressed),
+        None => format!(".{}", format.extension()),
+    }
+}
+
+/// complete files of the archive in `dir` in `format` and `compression`,
+/// sorted by their first height
+pub fn files(dir: &Path, format: Format, compression: Compression) -> Result<Vec<ArchiveFile>> {
+    let suffix = suffix(format, compression);
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let range = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(&suffix))
+            .and_then(|range| range.split_once('-'));
+        if let Some((Ok(first), Ok(last))) = range.map(|(f, l)| (f.parse(), l.parse())) {
+            files.push(ArchiveFile { first, last, path });
+        }
+    }
+
+    files.sort_by_key(|file| file.first);
+    Ok(files)
+}
+
+/// format and compression of the complete files in `dir`, which must all
+/// share them
+pub fn detect(dir: &Path) -> Result<(Format, Compression)> {
+    let mut found = vec![];
+    for format in [Format::Jsonl, Format::PbDelimited] {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            if !files(dir, format, compression)?.is_empty() {
+                found.push((format, compression));
+            }
+        }
+    }
+
+    match found[..] {
+        [found] => Ok(found),
+        [] => Err(anyhow::anyhow!("no archive files in {:?}", dir).into()),
+        _ => Err(anyhow::anyhow!("archive files of several formats in {:?}", dir).into()),
+    }
+}
+
+/// blocks of `network` in an archive file in `format` and `compression`
+pub fn blocks(
+    file: &ArchiveFile,
+    format: Format,
+    compression: Compression,
+    network: Network,
+) -> Result<impl Iterator<Item = Result<FirehoseBlock>> + Send> {
+    let input = File::open(&file.path)?;
+    let mut reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(BufReader::new(input)),
+        Compression::Gzip => Box::new(BufReader::new(GzDecoder::new(input))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::new(input)?)),
+    };
+
+    Ok(std::iter::from_fn(move || {
+        format.decode(&mut reader, &network).transpose()
+    }))
+}
+
+/// encoder of the file being written
+enum Encoder {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Encoder {
+    fn new(path: &Path, compression: Compression) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match compression {
+            Compression::None => Self::Plain(file),
+            Compression::Gzip => Self::Gzip(GzEncoder::new(file, GzLevel::default())),
+            Compression::Zstd => Self::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        match self {
+            Self::Plain(w) => w.write_all(bytes)?,
+            Self::Gzip(w) => w.write_all(bytes)?,
+            Self::Zstd(w) => w.write_all(bytes)?,
+        }
+        Ok(())
+    }
+
+    /// flush the encoder and sync the file
+    fn finish(self) -> Result<()> {
+        let file = match self {
+            Self::Plain(w) => w,
+            Self::Gzip(w) => w.finish()?,
+            Self::Zstd(w) => w.finish()?,
+        };
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// file being written
+struct Open {
+    first: u64,
+    last: u64,
+    blocks: u64,
+    bytes: u64,
+    path: PathBuf,
+    encoder: Encoder,
+}
+
+/// writer of consecutive blocks into an archive
+///
+/// ```rust
+/// use thegarii::{archive::{self, Compression, Format, Writer}, network::Network, types::{Block, FirehoseBlock}};
+///
+/// let dir = std::env::temp_dir().join(format!("thegarii-archive-doctest-{}", std::process::id()));
+///
+/// let block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+/// let mut writer = Writer::new(&dir, Format::Jsonl, Compression::Gzip, Network::mainnet())
+///     .unwrap()
+///     .with_blocks_per_file(2);
+/// assert_eq!(writer.resume_from(100).unwrap(), 100);
+///
+/// for height in 100..105 {
+///     let mut block = FirehoseBlock::from(block.clone());
+///     block.height = height;
+///     writer.write(block).unwrap();
+/// }
+/// writer.finish().unwrap();
+///
+/// let files = archive::files(&dir, Format::Jsonl, Compression::Gzip).unwrap();
+/// assert_eq!(files.iter().map(|f| (f.first, f.last)).collect::<Vec<_>>(), [(100, 101), (102, 103), (104, 104)]);
+///
+/// // an interrupted export resumes after the last complete file
+/// let writer = Writer::new(&dir, Format::Jsonl, Compression::Gzip, Network::mainnet()).unwrap();
+/// assert_eq!(writer.resume_from(100).unwrap(), 105);
+///
+/// assert_eq!(archive::detect(&dir).unwrap(), (Format::Jsonl, Compression::Gzip));
+/// let heights = archive::blocks(&files[1], Format::Jsonl, Compression::Gzip, Network::mainnet())
+///     .unwrap()
+///     .map(|block| block.unwrap().height)
+///     .collect::<Vec<_>>();
+/// assert_eq!(heights, [102, 103]);
+///
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct Writer {
+    dir: PathBuf,
+    format: Format,
+    compression: Compression,
+    blocks_per_file: u64,
+    max_file_size: Option<u64>,
+    network: Network,
+    open: Option<Open>,
+}
+
+impl Writer {
+    /// writer of an archive in `dir` of blocks of `network`, created if
+    /// missing, files being written by an interrupted writer are removed
+    pub fn new(
+        dir: &Path,
+        format: Format,
+        compression: Compression,
+        network: Network,
+    ) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == PARTIAL) {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            format,
+            compression,
+            blocks_per_file: u64::MAX,
+            max_file_size: None,
+            network,
+            open: None,
+        })
+    }
+
+    /// close files once they hold `blocks` blocks
+    pub fn with_blocks_per_file(mut self, blocks: u64) -> Self {
+        self.blocks_per_file = blocks.max(1);
+        self
+    }
+
+    /// close files once `bytes` have been written to them, before compression
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// height following the complete files holding the blocks from `start`
+    pub fn resume_from(&self, start: u64) -> Result<u64> {
+        let mut next = start;
+        for file in files(&self.dir, self.format, self.compression)? {
+            if file.first <= next && file.last >= next {
+                next = file.last + 1;
+            }
+        }
+
+        Ok(next)
+    }
+
+    /// append `block`, which must follow the last written one
+    pub fn write(&mut self, block: FirehoseBlock) -> Result<()> {
+        let height = block.height;
+        let mut open = match self.open.take() {
+            Some(open) if open.last + 1 == height => open,
+            Some(_) => return Err(Error::InvalidRange),
+            None => {
+                let path = self.dir.join(format!("{:012}.{}", height, PARTIAL));
+                Open {
+                    first: height,
+                    last: height,
+                    blocks: 0,
+                    bytes: 0,
+                    encoder: Encoder::new(&path, self.compression)?,
+                    path,
+                }
+            }
+        };
+
+        let bytes = self.format.encode(block, &self.network)?;
+        open.encoder.write_all(&bytes)?;
+        open.last = height;
+        open.blocks += 1;
+        open.bytes += bytes.len() as u64;
+
+        if open.blocks >= self.blocks_per_file
+            || self.max_file_size.is_some_and(|max| open.bytes >= max)
+        {
+            self.close(open)?;
+        } else {
+            self.open = Some(open);
+        }
+        Ok(())
+    }
+
+    /// complete the file being written
+    fn close(&self, open: Open) -> Result<()> {
+        open.encoder.finish()?;
+        let name = format!(
+            "{:012}-{:012}{}",
+            open.first,
+            open.last,
+            suffix(self.format, self.compression)
+        );
+        fs::rename(&open.path, self.dir.join(&name))?;
+
+        log::info!("exported blocks {}..={} to {}", open.first, open.last, name);
+        Ok(())
+    }
+
+    /// complete the file being written, if any
+    pub fn finish(mut self) -> Result<()> {
+        match self.open.take() {
+            Some(open) => self.close(open),
+            None => Ok(()),
+        }
+    }
+}
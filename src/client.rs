@@ -2,16 +2,219 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 
 //! arweave client
+#[cfg(feature = "full")]
+use crate::storage::Storage;
 use crate::{
+    concurrency::Concurrency,
+    encoding, metrics,
+    network::Network,
     result::{Error, Result},
-    types::{Block, FirehoseBlock, Transaction},
+    types::{Base64Url, Block, DataStatus, FirehoseBlock, Transaction, TxStatus},
+    verify::{self, TxRootPolicy},
     Env,
 };
-use futures::future::join_all;
+use bytes::Bytes;
+use futures::{
+    future::join_all,
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
 use rand::Rng;
 use reqwest::{Client as ReqwestClient, ClientBuilder, StatusCode};
 use serde::de::DeserializeOwned;
-use std::time::Duration;
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
+
+/// delay before the first retry of a request, doubled on each retry
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// failed endpoints are avoided for this duration
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(30);
+/// max delay between two rounds of fetching the transactions of a block
+const MAX_ROUND_DELAY: Duration = Duration::from_secs(60);
+
+/// what to do with a block whose transactions are still missing after all rounds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxFailurePolicy {
+    /// fail the block
+    Fail,
+    /// keep retrying the missing transactions until they are fetched
+    Wait,
+}
+
+impl FromStr for TxFailurePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "wait" => Ok(Self::Wait),
+            _ => {
+                Err(anyhow::anyhow!("invalid tx failure policy {}, expect fail or wait", s).into())
+            }
+        }
+    }
+}
+
+/// options of following the head with `Client::follow_blocks`
+#[derive(Clone, Debug)]
+pub struct Follow {
+    /// max blocks fetched at the same time
+    pub concurrency: usize,
+    /// blocks behind the head to stay, 0 follows the head itself
+    pub confirms: u64,
+    /// interval of checking the head once caught up
+    pub interval: Duration,
+    /// deepest reorg which can be notified, deeper ones fail the stream
+    pub max_reorg_depth: usize,
+}
+
+impl Default for Follow {
+    fn default() -> Self {
+        Self {
+            concurrency: 20,
+            confirms: 20,
+            interval: Duration::from_secs(60),
+            max_reorg_depth: 50,
+        }
+    }
+}
+
+/// event of `Client::follow_blocks`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockEvent {
+    /// next block of the chain
+    New(Box<FirehoseBlock>),
+    /// a previously emitted block has been replaced by a reorg and must be
+    /// discarded, undos are emitted from the highest block down to the fork
+    Undo { height: u64, indep_hash: Base64Url },
+}
+
+/// state of `Client::follow_blocks`
+struct FollowState<'c> {
+    client: &'c Client,
+    options: Follow,
+    next: u64,
+    /// emitted blocks which could still be reorged and the block below them,
+    /// the fork point of the deepest notified reorg, as (height, indep_hash)
+    recent: VecDeque<(u64, Base64Url)>,
+    batch: Option<BoxStream<'c, Result<FirehoseBlock>>>,
+    /// if the stream failed on a reorg deeper than the tracked blocks
+    failed: bool,
+}
+
+impl FollowState<'_> {
+    async fn next_event(&mut self) -> Result<BlockEvent> {
+        loop {
+            let batch = match &mut self.batch {
+                Some(batch) => batch,
+                None => {
+                    let head = self.client.get_current_block().await?.height;
+                    let target = head.saturating_sub(self.options.confirms);
+                    if target < self.next {
+                        tokio::time::sleep(self.options.interval).await;
+                        continue;
+                    }
+
+                    self.batch = Some(
+                        self.client
+                            .stream_blocks(self.next..=target, self.options.concurrency)
+                            .boxed(),
+                    );
+                    continue;
+                }
+            };
+
+            let block = match batch.next().await {
+                Some(Ok(block)) => block,
+                Some(Err(e)) => {
+                    // resume from the failed block on the next poll
+                    self.batch = None;
+                    return Err(e);
+                }
+                None => {
+                    self.batch = None;
+                    continue;
+                }
+            };
+
+            if let Some((height, indep_hash)) = self.recent.back() {
+                if block.previous_block != *indep_hash {
+                    let (height, indep_hash) = (*height, indep_hash.clone());
+                    self.recent.pop_back();
+                    if self.recent.is_empty() {
+                        self.failed = true;
+                        return Err(anyhow::anyhow!(
+                            "reorg at block {} is deeper than the {} tracked blocks",
+                            height,
+                            self.options.max_reorg_depth
+                        )
+                        .into());
+                    }
+
+                    // refetch from the undone block
+                    self.batch = None;
+                    self.next = height;
+                    tracing::warn!(height, "block {} {} has been reorged", height, indep_hash);
+                    return Ok(BlockEvent::Undo { height, indep_hash });
+                }
+            }
+
+            self.next = block.height + 1;
+            self.recent
+                .push_back((block.height, block.indep_hash.clone()));
+            if self.recent.len() > self.options.max_reorg_depth + 1 {
+                self.recent.pop_front();
+            }
+            return Ok(BlockEvent::New(Box::new(block)));
+        }
+    }
+}
+
+/// options of fetching the payload of format 2 transactions, which is not
+/// embedded in `/tx/{id}`
+#[derive(Clone, Debug)]
+pub struct DataOptions {
+    /// payloads bigger than this size in bytes are skipped
+    pub size_cap: u64,
+    /// only fetch payloads with these `Content-Type` tags, i.e. `image/png` or
+    /// `image/*`, fetch all payloads if empty
+    pub content_types: Vec<String>,
+}
+
+impl DataOptions {
+    /// if the content type of the transaction is accepted
+    pub fn accepts(&self, tx: &Transaction) -> bool {
+        if self.content_types.is_empty() {
+            return true;
+        }
+
+        let content_type = match tx.tags.iter().find(|tag| {
+            tag.decoded_name()
+                .map(|name| name.eq_ignore_ascii_case("Content-Type"))
+                .unwrap_or(false)
+        }) {
+            Some(tag) => match tag.decoded_value() {
+                Some(value) => value.to_ascii_lowercase(),
+                None => return false,
+            },
+            None => return false,
+        };
+
+        self.content_types.iter().any(|accepted| {
+            let accepted = accepted.to_ascii_lowercase();
+            match accepted.strip_suffix('*') {
+                Some(prefix) => content_type.starts_with(prefix),
+                None => content_type == accepted,
+            }
+        })
+    }
+}
 
 /// Arweave client
 pub struct Client {
@@ -19,16 +222,63 @@ pub struct Client {
     /// arweave endpoints
     pub endpoints: Vec<String>,
     retry: u8,
+    retry_delay: Duration,
+    concurrency: Option<Arc<Concurrency>>,
+    data: Option<DataOptions>,
+    failures: Mutex<HashMap<String, Instant>>,
+    tx_rounds: u8,
+    tx_failure: TxFailurePolicy,
+    tx_root: TxRootPolicy,
+    network: Network,
+    #[cfg(feature = "full")]
+    storage: Option<Arc<Storage>>,
+    #[cfg(feature = "full")]
+    tx_index: bool,
 }
 
 impl Client {
     /// get next endpoint
+    ///
+    /// endpoints failed within `FAILURE_COOLDOWN` are skipped as long as
+    /// other endpoints are available
     fn next_endpoint(&self) -> String {
-        self.endpoints[rand::thread_rng().gen_range(0..self.endpoints.len())].to_string()
+        let failures = self.failures.lock().expect("endpoint failures poisoned");
+        let healthy = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| {
+                failures
+                    .get(*endpoint)
+                    .map(|at| at.elapsed() > FAILURE_COOLDOWN)
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+
+        if healthy.is_empty() {
+            self.endpoints[rand::thread_rng().gen_range(0..self.endpoints.len())].to_string()
+        } else {
+            healthy[rand::thread_rng().gen_range(0..healthy.len())].to_string()
+        }
+    }
+
+    /// mark endpoint as failed or healthy
+    fn mark_endpoint(&self, endpoint: &str, failed: bool) {
+        let mut failures = self.failures.lock().expect("endpoint failures poisoned");
+        if failed {
+            failures.insert(endpoint.to_string(), Instant::now());
+        } else {
+            failures.remove(endpoint);
+        }
     }
 
-    /// new arweave client
-    pub fn new(endpoints: Vec<String>, timeout: Duration, retry: u8) -> Result<Self> {
+    /// new arweave client, checking blocks against the fork schedule of
+    /// `network`
+    pub fn new(
+        endpoints: Vec<String>,
+        timeout: Duration,
+        retry: u8,
+        network: Network,
+    ) -> Result<Self> {
         if endpoints.is_empty() {
             return Err(Error::EmptyEndpoints);
         }
@@ -39,61 +289,262 @@ impl Client {
             client,
             endpoints,
             retry,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            concurrency: None,
+            data: None,
+            failures: Default::default(),
+            tx_rounds: 1,
+            tx_failure: TxFailurePolicy::Fail,
+            tx_root: TxRootPolicy::Fail,
+            network,
+            #[cfg(feature = "full")]
+            storage: None,
+            #[cfg(feature = "full")]
+            tx_index: false,
         })
     }
 
+    /// fetch the transactions of a block in up to `rounds` rounds, each round
+    /// only retries the transactions missing from previous rounds, `policy`
+    /// decides what happens once all rounds failed
+    pub fn with_tx_rounds(mut self, rounds: u8, policy: TxFailurePolicy) -> Self {
+        self.tx_rounds = rounds.max(1);
+        self.tx_failure = policy;
+        self
+    }
+
+    /// what to do with blocks whose transactions do not hash to their
+    /// `tx_root`, their ids are checked anyway
+    pub fn with_tx_root(mut self, policy: TxRootPolicy) -> Self {
+        self.tx_root = policy;
+        self
+    }
+
+    /// wait `delay` before the first retry of a request, doubled on each retry
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// fetch the payload of format 2 transactions in firehose blocks
+    pub fn with_data(mut self, data: DataOptions) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// report request latency and failures to a concurrency controller
+    pub fn with_concurrency(mut self, concurrency: Arc<Concurrency>) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// network of the fetched blocks
+    ///
+    /// ```rust
+    /// use thegarii::{network::Network, Client};
+    ///
+    /// let endpoints = vec!["http://localhost:1984".into()];
+    /// let client = Client::new(endpoints, Default::default(), 0, Network::local()).unwrap();
+    /// assert_eq!(client.network().name, "local");
+    /// ```
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
     /// new client from environments
     pub fn from_env() -> Result<Self> {
-        let env = Env::new()?;
-        let client = ClientBuilder::new()
-            .gzip(true)
-            .timeout(Duration::from_millis(env.timeout))
-            .build()?;
+        Self::from_config(&Env::new()?)
+    }
 
-        Ok(Self {
-            client,
-            endpoints: env.endpoints,
-            retry: env.retry,
-        })
+    /// read firehose blocks from the local block database before fetching them
+    #[cfg(feature = "full")]
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// index transaction ids of stored blocks
+    #[cfg(feature = "full")]
+    pub fn with_tx_index(mut self, tx_index: bool) -> Self {
+        self.tx_index = tx_index;
+        self
+    }
+
+    /// local block database, if any
+    #[cfg(feature = "full")]
+    pub fn storage(&self) -> Option<&Arc<Storage>> {
+        self.storage.as_ref()
+    }
+
+    /// new client from `env`, opens the block database of `env` with the
+    /// `full` feature
+    pub fn from_config(env: &Env) -> Result<Self> {
+        #[cfg_attr(not(feature = "full"), allow(unused_mut))]
+        let mut client = Self::remote(env)?;
+
+        #[cfg(feature = "full")]
+        {
+            client = client
+                .with_storage(Storage::open(&env.db_path)?)
+                .with_tx_index(env.index_txs);
+        }
+
+        Ok(client)
+    }
+
+    /// new client from `env` fetching every block from the endpoints, the
+    /// block database is left to other processes
+    pub fn remote(env: &Env) -> Result<Self> {
+        let mut client = Self::new(
+            env.endpoints.clone(),
+            Duration::from_millis(env.timeout),
+            env.retry,
+            env.network.clone(),
+        )?
+        .with_retry_delay(Duration::from_millis(env.retry_delay))
+        .with_tx_rounds(env.tx_rounds, env.tx_failure)
+        .with_tx_root(env.tx_root_check);
+
+        if env.fetch_data {
+            client = client.with_data(DataOptions {
+                size_cap: env.data_size_cap,
+                content_types: env.data_content_types.clone(),
+            });
+        }
+
+        Ok(client)
+    }
+
+    /// single http get request on `endpoint`, returns `None` on unexpected
+    /// response status
+    async fn attempt(&self, endpoint: &str, path: &str) -> Result<Option<Bytes>> {
+        let now = Instant::now();
+        metrics::REQUESTS.with_label_values(&[endpoint]).inc();
+
+        let result = match self
+            .client
+            .get(format!("{}/{}", endpoint, path))
+            .send()
+            .await
+        {
+            Ok(r) if r.status() == StatusCode::OK => r.bytes().await.map(Some),
+            Ok(r) => {
+                let status = r.status();
+                if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                    if let Some(concurrency) = &self.concurrency {
+                        concurrency.on_error();
+                    }
+                }
+
+                tracing::debug!(status = status.as_u16(), "unexpected response status");
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        };
+
+        let duration_ms = now.elapsed().as_millis() as u64;
+        match result {
+            Ok(Some(body)) => {
+                self.mark_endpoint(endpoint, false);
+                metrics::REQUEST_LATENCY
+                    .with_label_values(&[endpoint])
+                    .observe(now.elapsed().as_secs_f64());
+                metrics::BYTES_FETCHED.inc_by(body.len() as u64);
+                if let Some(concurrency) = &self.concurrency {
+                    concurrency.on_response(now.elapsed());
+                }
+
+                tracing::debug!(duration_ms, bytes = body.len(), "request succeeded");
+                Ok(Some(body))
+            }
+            Ok(None) => {
+                self.mark_endpoint(endpoint, true);
+                metrics::REQUEST_ERRORS.with_label_values(&[endpoint]).inc();
+                Ok(None)
+            }
+            Err(e) => {
+                self.mark_endpoint(endpoint, true);
+                metrics::REQUEST_ERRORS.with_label_values(&[endpoint]).inc();
+                if e.is_timeout() || e.is_connect() {
+                    if let Some(concurrency) = &self.concurrency {
+                        concurrency.on_error();
+                    }
+                }
+
+                tracing::debug!(duration_ms, error = %e, "request failed");
+                Err(e.into())
+            }
+        }
     }
 
     /// http get request with base url
     async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.get_with(path, |body| encoding::from_slice(body, path))
+            .await
+    }
+
+    /// http get request with base url, returns the raw response body
+    async fn get_raw(&self, path: &str) -> Result<Bytes> {
+        self.get_with(path, |body| Ok(body.clone())).await
+    }
+
+    /// http get request with base url, transport errors and responses
+    /// failing to `parse`, i.e. truncated bodies, are retried like failed
+    /// requests, the last error is returned once retries are exhausted
+    async fn get_with<T>(&self, path: &str, parse: impl Fn(&Bytes) -> Result<T>) -> Result<T> {
         let mut retried = 0;
-        let mut ms_between_retries = 10000;
+        let mut ms_between_retries = self.retry_delay.as_millis() as u64;
         loop {
-            match self
-                .client
-                .get(&format!("{}/{}", self.next_endpoint(), path))
-                .send()
+            let endpoint = self.next_endpoint();
+            let request = tracing::debug_span!("request", %endpoint, path, attempt = retried + 1);
+
+            let failure = match self
+                .attempt(&endpoint, path)
+                .instrument(request.clone())
                 .await
             {
-                Ok(r) => match r.status() {
-                    StatusCode::OK => match r.json::<T>().await {
-                        Ok(r) => return Ok(r),
-                        Err(e) => return Err(e.into()),
-                    },
-                    _ => {
-                        if retried < self.retry {
-                            let duration = Duration::from_millis(ms_between_retries);
-                            tokio::time::sleep(duration).await;
-                            retried += 1;
-                            ms_between_retries *= 2;
-                            log::info!(
-                                "retrying request in {} second(s), at attempt {}, attempts left {}",
-                                Duration::as_secs(&duration),
-                                retried,
-                                self.retry - retried
-                            );
-                            continue;
-                        }
-                        return Err(Error::RetriesReached);
+                Ok(Some(body)) => match parse(&body) {
+                    Ok(parsed) => return Ok(parsed),
+                    Err(e) => {
+                        self.mark_endpoint(&endpoint, true);
+                        metrics::REQUEST_ERRORS
+                            .with_label_values(&[&endpoint])
+                            .inc();
+                        request.in_scope(|| tracing::debug!(error = %e, "invalid response body"));
+                        Some(e)
                     }
                 },
-                Err(e) => {
-                    return Err(e.into());
+                Ok(None) => None,
+                Err(e) => Some(e),
+            };
+
+            if retried < self.retry {
+                let duration = Duration::from_millis(ms_between_retries);
+                retried += 1;
+                ms_between_retries *= 2;
+                metrics::RETRIES.with_label_values(&[&endpoint]).inc();
+
+                let retry = tracing::info_span!(
+                    "retry",
+                    %endpoint,
+                    path,
+                    attempt = retried,
+                    delay_ms = ms_between_retries / 2
+                );
+                async {
+                    tracing::info!(
+                        "retrying request in {} second(s), at attempt {}, attempts left {}",
+                        Duration::as_secs(&duration),
+                        retried,
+                        self.retry - retried
+                    );
+                    tokio::time::sleep(duration).await;
                 }
+                .instrument(retry)
+                .await;
+                continue;
             }
+            return Err(failure.unwrap_or(Error::RetriesReached));
         }
     }
 
@@ -174,8 +625,21 @@ impl Client {
     ///   assert_eq!(tx, serde_json::from_str::<Transaction>(&json).unwrap());
     /// }
     /// ```
+    #[tracing::instrument(name = "fetch_tx", skip(self, id), fields(tx_id = %id))]
     pub async fn get_tx_by_id(&self, id: &str) -> Result<Transaction> {
-        self.get(&format!("tx/{}", id)).await
+        let now = Instant::now();
+        let tx = self.get(&format!("tx/{}", id)).await?;
+
+        tracing::debug!(
+            duration_ms = now.elapsed().as_millis() as u64,
+            "fetched transaction"
+        );
+        Ok(tx)
+    }
+
+    /// get the status of an arweave transaction, i.e. the block containing it
+    pub async fn get_tx_status(&self, id: &str) -> Result<TxStatus> {
+        self.get(&format!("tx/{}/status", id)).await
     }
 
     /// get arweave transaction data by id
@@ -190,19 +654,44 @@ impl Client {
     ///   assert_eq!(tx, json);
     /// }
     /// ```
-    ///
-    /// # NOTE
-    ///
-    /// timeout and retry don't work for this reqeust since we're not using
-    /// this api in the polling service.
+    #[tracing::instrument(name = "fetch_tx_data", skip(self, id), fields(tx_id = %id))]
     pub async fn get_tx_data_by_id(&self, id: &str) -> Result<String> {
-        Ok(self
-            .client
-            .get(&format!("{}/tx/{}/data", self.next_endpoint(), id))
-            .send()
-            .await?
-            .text()
-            .await?)
+        let data = self.get_raw(&format!("tx/{}/data", id)).await?;
+        if !data
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+        {
+            return Err(anyhow::anyhow!("tx {} data is not base64url encoded", id).into());
+        }
+
+        // ascii, as checked above
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    /// fetch the payload of format 2 transactions wrt the data options
+    async fn resolve_tx_data(&self, mut tx: Transaction) -> Result<Transaction> {
+        let options = match &self.data {
+            Some(options) => options,
+            None => return Ok(tx),
+        };
+
+        if tx.format != Some(2) || !tx.data.is_empty() || tx.data_size.is_zero() {
+            return Ok(tx);
+        }
+
+        let within_cap = tx
+            .data_size
+            .to_u64()
+            .map(|size| size <= options.size_cap)
+            .unwrap_or(false);
+        if within_cap && options.accepts(&tx) {
+            tx.data = self.get_tx_data_by_id(&tx.id.to_string()).await?.parse()?;
+            tx.data_status = DataStatus::Fetched;
+        } else {
+            tx.data_status = DataStatus::Skipped;
+        }
+
+        Ok(tx)
     }
 
     /// get and parse firehose blocks by height
@@ -218,40 +707,303 @@ impl Client {
     ///   block_without_txs.txs = vec![];
     ///
     ///   assert_eq!(block_without_txs, rt.block_on(client.get_block_by_height(269512)).unwrap().into());
-    ///   for (idx, tx) in firehose_block.txs.iter().map(|tx| tx.id.clone()).enumerate() {
+    ///   for (idx, tx) in firehose_block.txs.iter().map(|tx| tx.id.to_string()).enumerate() {
     ///     assert_eq!(firehose_block.txs[idx], rt.block_on(client.get_tx_by_id(&tx)).unwrap());
     ///   }
     /// }
     /// ```
+    #[tracing::instrument(name = "fetch_block", skip(self))]
     pub async fn get_firehose_block_by_height(&self, height: u64) -> Result<FirehoseBlock> {
-        log::info!("resolving firehose block {}", height);
+        let now = Instant::now();
+        #[cfg(feature = "full")]
+        if let Some(block) = self.stored_block(height).await? {
+            return Ok(block);
+        }
+
+        tracing::info!("resolving firehose block {}", height);
 
         let block = self.get_block_by_height(height).await?;
-        let txs: Vec<Transaction> = join_all(block.txs.iter().map(|tx| self.get_tx_by_id(tx)))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<Transaction>>>()?;
+        let firehose_block = self.resolve_txs(block).await?;
+
+        tracing::debug!(
+            txs = firehose_block.txs.len(),
+            duration_ms = now.elapsed().as_millis() as u64,
+            "resolved firehose block"
+        );
+        Ok(firehose_block)
+    }
+
+    /// get and parse firehose block by `indep_hash`
+    #[tracing::instrument(name = "fetch_block", skip(self))]
+    pub async fn get_firehose_block_by_hash(&self, hash: &str) -> Result<FirehoseBlock> {
+        let block = self.get_block_by_hash(hash).await?;
+        #[cfg(feature = "full")]
+        if let Some(block) = self.stored_block(block.height).await? {
+            if block.indep_hash.to_string() == hash {
+                return Ok(block);
+            }
+        }
+
+        tracing::info!("resolving firehose block {}", block.height);
+        self.resolve_txs(block).await
+    }
+
+    /// check `txs` are the ones of `block`, their `tx_root` wrt the tx root
+    /// policy
+    fn check_txs(&self, block: &Block, txs: &[Transaction]) -> Result<()> {
+        verify::verify_tx_ids(block, txs)?;
+        match self.tx_root {
+            TxRootPolicy::Fail => verify::verify_tx_root(block, txs, &self.network)?,
+            TxRootPolicy::Warn => {
+                if let Err(e) = verify::verify_tx_root(block, txs, &self.network) {
+                    tracing::warn!(height = block.height, "{}", e);
+                }
+            }
+            TxRootPolicy::Off => {}
+        }
+
+        Ok(())
+    }
+
+    /// fetch the transactions of `block` wrt the tx rounds and failure policy
+    async fn resolve_txs(&self, block: Block) -> Result<FirehoseBlock> {
+        let height = block.height;
+        let ids = &block.txs;
+
+        // transactions fetched so far, kept across rounds
+        let mut txs: Vec<Option<Transaction>> = vec![None; block.txs.len()];
+        let mut round: u32 = 0;
+        let txs = loop {
+            let missing = txs
+                .iter()
+                .enumerate()
+                .filter(|(_, tx)| tx.is_none())
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+
+            let error = if missing.is_empty() {
+                // all fetched, check they are the ones the block commits to,
+                // refetch all of them otherwise, from other endpoints if any
+                let fetched = mem::take(&mut txs)
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                match self.check_txs(&block, &fetched) {
+                    Ok(()) => break fetched,
+                    Err(e) => {
+                        txs = vec![None; block.txs.len()];
+                        e
+                    }
+                }
+            } else {
+                let mut error = None;
+                for result in join_all(missing.iter().map(|&idx| async move {
+                    let tx = self.get_tx_by_id(&ids[idx].to_string()).await?;
+                    // another tx is refetched alone, before checking tx_root
+                    if tx.id != ids[idx] {
+                        return Err(Error::TxsMismatch {
+                            height,
+                            reason: format!("tx {} is {}, expected {}", idx, tx.id, ids[idx]),
+                        });
+                    }
+                    Ok::<_, Error>((idx, self.resolve_tx_data(tx).await?))
+                }))
+                .await
+                {
+                    match result {
+                        Ok((idx, tx)) => txs[idx] = Some(tx),
+                        Err(e) => error = Some(e),
+                    }
+                }
+
+                match error {
+                    Some(e) => e,
+                    None => continue,
+                }
+            };
+
+            round += 1;
+            let failed = txs.iter().filter(|tx| tx.is_none()).count();
+            // mismatches are not waited for, they fail the block once rounds
+            // are exhausted
+            let mismatch = matches!(error, Error::TxsMismatch { .. });
+            if round >= self.tx_rounds as u32
+                && (self.tx_failure == TxFailurePolicy::Fail || mismatch)
+            {
+                if mismatch {
+                    return Err(error);
+                }
+                return Err(Error::TxsUnavailable {
+                    height,
+                    missing: failed,
+                    source: Box::new(error),
+                });
+            }
+
+            let delay = Duration::from_secs(1 << round.min(6)).min(MAX_ROUND_DELAY);
+            if mismatch {
+                tracing::warn!(
+                    round,
+                    "txs of block {} mismatch after round {}, refetching them in {}s: {}",
+                    height,
+                    round,
+                    delay.as_secs(),
+                    error
+                );
+            } else {
+                tracing::warn!(
+                    round,
+                    missing = failed,
+                    "{} of {} txs of block {} are missing after round {}, retrying in {}s: {}",
+                    failed,
+                    txs.len(),
+                    height,
+                    round,
+                    delay.as_secs(),
+                    error
+                );
+            }
+            tokio::time::sleep(delay).await;
+        };
 
         let mut firehose_block: FirehoseBlock = block.into();
         firehose_block.txs = txs;
         Ok(firehose_block)
     }
 
-    /// poll blocks from iterator
+    /// get firehose block from the local block database, payloads skipped
+    /// when storing it are resolved again wrt the current data options
+    #[cfg(feature = "full")]
+    async fn stored_block(&self, height: u64) -> Result<Option<FirehoseBlock>> {
+        let mut block = match self.storage.as_ref().map(|s| s.get_block(height)) {
+            Some(Ok(Some(block))) => block,
+            Some(Err(e)) => return Err(e),
+            _ => return Ok(None),
+        };
+
+        if self.data.is_some() {
+            let txs = std::mem::take(&mut block.txs);
+            block.txs = join_all(txs.into_iter().map(|tx| self.resolve_tx_data(tx)))
+                .await
+                .into_iter()
+                .collect::<Result<_>>()?;
+        }
+
+        tracing::debug!(txs = block.txs.len(), "read firehose block from database");
+        Ok(Some(block))
+    }
+
+    /// store an irreversible firehose block in the local block database, if
+    /// any, and index its transactions if enabled
+    pub fn store(&self, block: &FirehoseBlock) -> Result<()> {
+        #[cfg(feature = "full")]
+        if let Some(storage) = &self.storage {
+            storage.put_block(block)?;
+            if self.tx_index {
+                storage.index_txs(block)?;
+            }
+        }
+
+        #[cfg(not(feature = "full"))]
+        let _ = block;
+
+        Ok(())
+    }
+
+    /// ordered stream of the firehose blocks at the heights of `blocks`, i.e. a
+    /// range, fetching up to `concurrency` blocks at the same time
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    ///
+    /// let client = thegarii::Client::from_env().unwrap();
+    /// let rt = tokio::runtime::Runtime::new().unwrap();
+    ///
+    /// let heights = rt.block_on(
+    ///     client
+    ///         .stream_blocks(269512..=269515, 2)
+    ///         .map(|block| block.unwrap().height)
+    ///         .collect::<Vec<_>>(),
+    /// );
+    /// assert_eq!(heights, vec![269512, 269513, 269514, 269515]);
+    /// ```
+    pub fn stream_blocks<'a, Blocks>(
+        &'a self,
+        blocks: Blocks,
+        concurrency: usize,
+    ) -> impl Stream<Item = Result<FirehoseBlock>> + Send + 'a
+    where
+        Blocks: IntoIterator<Item = u64>,
+        Blocks::IntoIter: Send + 'a,
+    {
+        stream::iter(blocks)
+            .map(move |height| self.get_firehose_block_by_height(height))
+            .buffered(concurrency.max(1))
+    }
+
+    /// endless ordered stream of the firehose blocks from `start`, following
+    /// the head wrt `options`
+    ///
+    /// blocks are checked against the previously emitted ones, a reorg emits
+    /// `BlockEvent::Undo` for each replaced block before the new branch.
+    /// errors are yielded and the stream resumes from the failed block, but a
+    /// reorg deeper than `max_reorg_depth` ends it.
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    /// use thegarii::client::{BlockEvent, Follow};
+    ///
+    /// let client = thegarii::Client::from_env().unwrap();
+    /// let rt = tokio::runtime::Runtime::new().unwrap();
+    ///
+    /// rt.block_on(async {
+    ///     let mut events = client.follow_blocks(1_000_000, Follow::default());
+    ///     while let Some(event) = events.next().await {
+    ///         match event.unwrap() {
+    ///             BlockEvent::New(block) => println!("new block {}", block.height),
+    ///             BlockEvent::Undo { height, .. } => println!("undo block {}", height),
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn follow_blocks(&self, start: u64, options: Follow) -> BoxStream<'_, Result<BlockEvent>> {
+        let state = FollowState {
+            client: self,
+            options,
+            next: start,
+            recent: VecDeque::new(),
+            batch: None,
+            failed: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.failed {
+                return None;
+            }
+
+            let event = state.next_event().await;
+            Some((event, state))
+        })
+        .boxed()
+    }
+
+    /// poll blocks from iterator, fetching up to `concurrency` blocks at the
+    /// same time, see `stream_blocks`
     ///
     /// ```rust
     /// let client = thegarii::Client::from_env().unwrap();
     /// let rt = tokio::runtime::Runtime::new().unwrap();
     ///
-    /// rt.block_on(client.poll(269512..269515)).unwrap();
+    /// rt.block_on(client.poll(269512..269515, 2)).unwrap();
     /// ```
-    pub async fn poll<Blocks>(&self, blocks: Blocks) -> Result<Vec<FirehoseBlock>>
+    pub async fn poll<Blocks>(
+        &self,
+        blocks: Blocks,
+        concurrency: usize,
+    ) -> Result<Vec<FirehoseBlock>>
     where
-        Blocks: Iterator<Item = u64> + Sized,
+        Blocks: Iterator<Item = u64> + Send,
     {
-        join_all(blocks.map(|block| self.get_firehose_block_by_height(block)))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<FirehoseBlock>>>()
+        self.stream_blocks(blocks, concurrency).try_collect().await
     }
 }
@@ -1,8 +1,30 @@
 // Copyright 2021 ChainSafe Systems
 // SPDX-License-Identifier: LGPL-3.0-only
-use crate::{polling::Polling, Env, Result};
+use crate::{
+    filter::Filter,
+    health::Health,
+    metrics,
+    polling::Polling,
+    replay::Source,
+    server::{self, response},
+    Env, Result,
+};
+use http_body_util::Full;
+use hyper::{body::Bytes, Response, StatusCode};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use structopt::StructOpt;
 
+/// json response of a health check
+fn health_response((ok, details): (bool, serde_json::Value)) -> Response<Full<Bytes>> {
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    response(status, "application/json", details.to_string())
+}
+
 /// console service
 #[derive(Debug, StructOpt)]
 pub struct Console {
@@ -21,6 +43,24 @@ pub struct Console {
     /// reduce Firehose logs block output by just showing the length (not good for production!)
     #[structopt(short = "q", long)]
     quiet: bool,
+    /// only emit transactions matching this filter, i.e.
+    /// `tag:App-Name=ArDrive and (tag:Content-Type=image/png or not owner:<address>)`
+    #[structopt(long)]
+    filter: Option<Filter>,
+    /// address of the local http server exposing prometheus metrics on /metrics,
+    /// liveness on /healthz and readiness on /readyz
+    #[structopt(long)]
+    http_addr: Option<SocketAddr>,
+    /// seconds within which the block pointer must have advanced to be ready
+    #[structopt(long, default_value = "600")]
+    ready_window: u64,
+    /// seconds within which the poll loop must have progressed to be live
+    #[structopt(long, default_value = "600")]
+    live_window: u64,
+    /// emit blocks of a local source instead of fetching them, an export
+    /// `archive:<dir>`, `res/` shaped `json:<dir>` or the block database `db`
+    #[structopt(long)]
+    replay: Option<Source>,
 }
 
 impl Console {
@@ -28,16 +68,48 @@ impl Console {
     pub async fn exec(&self, env: Env) -> Result<()> {
         log::debug!("\n{:?}", self);
         log::info!("start polling blocks...");
+        if self.replay.is_some() && self.start.as_deref() == Some("live") {
+            return Err(anyhow::anyhow!("replay cannot start from the live block").into());
+        }
+
+        let health = Arc::new(Health::new(
+            Duration::from_secs(self.ready_window),
+            Duration::from_secs(self.live_window),
+        ));
+
+        if let Some(addr) = self.http_addr {
+            let health = health.clone();
+            tokio::spawn(async move {
+                let result = server::serve(addr, move |request| match request.uri().path() {
+                    "/metrics" => response(
+                        StatusCode::OK,
+                        "text/plain; version=0.0.4",
+                        metrics::gather(),
+                    ),
+                    "/healthz" => health_response(health.live()),
+                    "/readyz" => health_response(health.ready()),
+                    _ => response(StatusCode::NOT_FOUND, "text/plain", "not found"),
+                })
+                .await;
+
+                if let Err(e) = result {
+                    log::error!("http server on {} stopped: {:?}", addr, e);
+                }
+            });
+        }
 
         let mut polling = Polling::new(
             self.data_directory.to_string(),
             self.end,
             env,
             self.forever,
+            health,
             self.start.clone(),
             self.quiet,
         )
-        .await?;
+        .await?
+        .with_filter(self.filter.clone())
+        .with_replay(self.replay.as_ref())?;
 
         if let Err(e) = polling.start().await {
             log::error!("{:?}", e);
@@ -0,0 +1,78 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+use crate::{
+    firehose::{self, Render},
+    Error, Result,
+};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// decode firehose logs into JSON
+#[derive(Debug, StructOpt)]
+pub struct Decode {
+    /// file of FIRE lines or base64 protobuf payloads, stdin if not set
+    file: Option<PathBuf>,
+    /// render bytes as hex instead of base64url
+    #[structopt(long)]
+    hex: bool,
+    /// pretty print the decoded blocks
+    #[structopt(long)]
+    pretty: bool,
+}
+
+impl Decode {
+    pub async fn exec(&self) -> Result<()> {
+        let input: Box<dyn BufRead> = match &self.file {
+            Some(path) => Box::new(BufReader::new(File::open(path)?)),
+            None => Box::new(BufReader::new(io::stdin())),
+        };
+        let render = Render {
+            hex: self.hex,
+            utf8_tags: true,
+            text_roots: true,
+        };
+
+        let (mut decoded, mut failed) = (0, 0);
+        for (number, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("FIRE INIT") {
+                continue;
+            }
+
+            let block = if line.starts_with("FIRE ") {
+                firehose::parse_block_line(line)
+            } else {
+                firehose::parse_payload(line)
+            };
+            match block {
+                Ok(block) => {
+                    let json = render.block_json(&block);
+                    if self.pretty {
+                        println!("{}", serde_json::to_string_pretty(&json)?);
+                    } else {
+                        println!("{}", json);
+                    }
+                    decoded += 1;
+                }
+                Err(e) => {
+                    log::error!("line {}: {}", number + 1, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        if failed > 0 {
+            return Err(Error::InvalidPayload(format!(
+                "{} of {} lines failed to decode",
+                failed,
+                decoded + failed
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+use crate::{
+    archive::{Compression, Format, Writer},
+    Client, Env, Error, Result,
+};
+use futures::StreamExt;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// export a range of blocks to an archive
+#[derive(Debug, StructOpt)]
+pub struct Export {
+    /// first block of the range
+    #[structopt(short, long)]
+    start: u64,
+    /// last block of the range, included
+    #[structopt(short, long)]
+    end: u64,
+    /// encoding of the blocks, jsonl or pb-delimited
+    #[structopt(long, default_value = "jsonl")]
+    format: Format,
+    /// directory of the archive
+    #[structopt(short, long)]
+    out: PathBuf,
+    /// blocks per file
+    #[structopt(long, default_value = "1000")]
+    blocks_per_file: u64,
+    /// bytes written to a file before starting the next one, before compression
+    #[structopt(long)]
+    max_file_size: Option<u64>,
+    /// compression of the files, none, gzip or zstd
+    #[structopt(long, default_value = "none")]
+    compression: Compression,
+}
+
+impl Export {
+    pub async fn exec(&self, env: Env) -> Result<()> {
+        if self.start > self.end {
+            return Err(Error::InvalidRange);
+        }
+
+        let mut writer = Writer::new(
+            &self.out,
+            self.format,
+            self.compression,
+            env.network.clone(),
+        )?
+        .with_blocks_per_file(self.blocks_per_file);
+        if let Some(size) = self.max_file_size {
+            writer = writer.with_max_file_size(size);
+        }
+
+        let start = writer.resume_from(self.start)?;
+        if start > self.end {
+            log::info!("blocks {}..={} are exported already", self.start, self.end);
+            return Ok(());
+        }
+        if start > self.start {
+            log::info!("resuming export from block {}", start);
+        }
+
+        let client = Client::from_config(&env)?;
+        let mut blocks = client.stream_blocks(start..=self.end, env.batch_blocks as usize);
+        while let Some(block) = blocks.next().await {
+            writer.write(block?)?;
+        }
+
+        writer.finish()
+    }
+}
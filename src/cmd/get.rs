@@ -1,20 +1,205 @@
 // Copyright 2021 ChainSafe Systems
 // SPDX-License-Identifier: LGPL-3.0-only
-use crate::{Client, Result};
+use crate::{
+    firehose,
+    network::Network,
+    pb,
+    types::{Block, FirehoseBlock},
+    Client, Env, Error, Result,
+};
+use base64::{engine::general_purpose, Engine as _};
+use futures::{stream, StreamExt};
+use prost::Message;
+use std::{
+    io::{self, Write},
+    ops::RangeInclusive,
+    str::FromStr,
+};
 use structopt::StructOpt;
 
+/// output of the `get` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Arweave JSON as returned by `/block/height/{height}`
+    Arweave,
+    /// `FirehoseBlock` JSON
+    Firehose,
+    /// protobuf `pb::Block`, a single block only
+    Pb,
+    /// length-delimited protobuf `pb::Block`s
+    PbDelimited,
+    /// base64 protobuf `pb::Block`, one per line
+    Base64,
+    /// FIRE BLOCK lines as emitted by the console
+    Fire,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "arweave" => Ok(Self::Arweave),
+            "firehose" => Ok(Self::Firehose),
+            "pb" => Ok(Self::Pb),
+            "pb-delimited" => Ok(Self::PbDelimited),
+            "base64" => Ok(Self::Base64),
+            "fire" => Ok(Self::Fire),
+            _ => Err(anyhow::anyhow!(
+                "invalid format {}, expect arweave, firehose, pb, pb-delimited, base64 or fire",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// block heights, a height `100` or a range `100..110`, `100..=110`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heights(pub RangeInclusive<u64>);
+
+impl FromStr for Heights {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let range = if let Some((start, end)) = s.split_once("..=") {
+            start.parse()?..=end.parse()?
+        } else if let Some((start, end)) = s.split_once("..") {
+            let end: u64 = end.parse()?;
+            start.parse()?..=end.checked_sub(1).ok_or(Error::InvalidRange)?
+        } else {
+            let height = s.parse()?;
+            height..=height
+        };
+
+        if range.is_empty() {
+            return Err(Error::InvalidRange);
+        }
+        Ok(Self(range))
+    }
+}
+
 #[derive(StructOpt, Debug)]
 pub struct Get {
-    /// block number
-    pub height: u64,
+    /// block height or range of heights, i.e. `100`, `100..110` or `100..=110`
+    #[structopt(required_unless_one = &["hash", "tx"])]
+    pub heights: Option<Heights>,
+    /// indep_hash of the block
+    #[structopt(long, conflicts_with_all = &["heights", "tx"])]
+    pub hash: Option<String>,
+    /// id of a transaction, gets the block containing it
+    #[structopt(long, conflicts_with_all = &["heights", "hash"])]
+    pub tx: Option<String>,
+    /// output format, arweave, firehose, pb, pb-delimited, base64 or fire
+    #[structopt(long, default_value = "firehose")]
+    pub format: Format,
+    /// read blocks from the block database and store the irreversible ones,
+    /// the database must not be open by another process
+    #[cfg(feature = "full")]
+    #[structopt(long)]
+    pub store: bool,
 }
 
 impl Get {
-    pub async fn exec(&self) -> Result<()> {
-        let client = Client::from_env()?;
-        let block = client.get_firehose_block_by_height(self.height).await?;
+    /// if blocks go through the block database
+    fn store(&self) -> bool {
+        #[cfg(feature = "full")]
+        return self.store;
+        #[cfg(not(feature = "full"))]
+        false
+    }
+
+    /// height of the block containing transaction `id`, from the transaction
+    /// index if any
+    async fn tx_height(client: &Client, id: &str) -> Result<u64> {
+        #[cfg(feature = "full")]
+        if let Some(storage) = client.storage() {
+            if let Some(location) = storage.lookup_tx(id)? {
+                return Ok(location.height);
+            }
+        }
+
+        Ok(client.get_tx_status(id).await?.block_height)
+    }
+
+    /// write `block` of `network` to `out` wrt the output format
+    fn write(
+        &self,
+        out: &mut impl Write,
+        block: FirehoseBlock,
+        network: &Network,
+        confirms: u64,
+    ) -> Result<()> {
+        match self.format {
+            Format::Arweave => writeln!(
+                out,
+                "{}",
+                serde_json::to_string_pretty(&Block::from(&block))?
+            )?,
+            Format::Firehose => writeln!(out, "{}", serde_json::to_string_pretty(&block)?)?,
+            Format::Pb => out.write_all(&pb::Block::new(block, network).encode_to_vec())?,
+            Format::PbDelimited => {
+                out.write_all(&pb::Block::new(block, network).encode_length_delimited_to_vec())?
+            }
+            Format::Base64 => writeln!(
+                out,
+                "{}",
+                general_purpose::STANDARD.encode(pb::Block::new(block, network).encode_to_vec())
+            )?,
+            Format::Fire => writeln!(
+                out,
+                "{}",
+                firehose::block_line(block, network, confirms, false)?
+            )?,
+        }
+
+        Ok(())
+    }
+
+    pub async fn exec(&self, env: Env) -> Result<()> {
+        // the database is locked by a running console, only open it on demand
+        let client = if self.store() {
+            Client::from_config(&env)?
+        } else {
+            Client::remote(&env)?
+        };
+
+        let heights = match (&self.heights, &self.tx) {
+            (Some(heights), _) => Some(heights.0.clone()),
+            (None, Some(id)) => {
+                let height = Self::tx_height(&client, id).await?;
+                Some(height..=height)
+            }
+            (None, None) => None,
+        };
+        if self.format == Format::Pb && heights.as_ref().is_some_and(|h| h.end() > h.start()) {
+            return Err(anyhow::anyhow!("pb holds a single block, use pb-delimited").into());
+        }
+
+        // only irreversible blocks go to the database
+        let head = if self.store() {
+            Some(client.get_current_block().await?.height)
+        } else {
+            None
+        };
+
+        let mut blocks = match (heights, &self.hash) {
+            (Some(heights), _) => client
+                .stream_blocks(heights, env.batch_blocks as usize)
+                .boxed(),
+            (None, Some(hash)) => stream::once(client.get_firehose_block_by_hash(hash)).boxed(),
+            (None, None) => return Err(Error::InvalidRange),
+        };
 
-        println!("{}", serde_json::to_string_pretty(&block)?);
+        let mut out = io::stdout().lock();
+        while let Some(block) = blocks.next().await {
+            let block = block?;
+            if head.is_some_and(|head| block.height + env.confirms <= head) {
+                client.store(&block)?;
+            }
+            self.write(&mut out, block, client.network(), env.confirms)?;
+        }
+        out.flush()?;
         Ok(())
     }
 }
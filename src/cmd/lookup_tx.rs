@@ -0,0 +1,43 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+use crate::{storage::Storage, Env, Error, Result};
+use serde_json::json;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct LookupTx {
+    /// transaction ids
+    pub ids: Vec<String>,
+    /// rebuild the transaction index from stored blocks first
+    #[structopt(long)]
+    pub rebuild: bool,
+}
+
+impl LookupTx {
+    pub async fn exec(&self, env: Env) -> Result<()> {
+        let storage = Storage::open(&env.db_path)?;
+
+        if self.rebuild {
+            let indexed = storage.rebuild_tx_index()?;
+            log::info!("indexed {} txs of stored blocks", indexed);
+        }
+
+        for id in &self.ids {
+            let location = storage
+                .lookup_tx(id)?
+                .ok_or_else(|| Error::TxNotFound(id.clone()))?;
+
+            println!(
+                "{}",
+                json!({
+                    "id": id,
+                    "height": location.height,
+                    "indep_hash": location.indep_hash,
+                    "position": location.position,
+                })
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+use crate::{
+    server::{self, response},
+    storage::Storage,
+    types::{Block, FirehoseBlock},
+    Env, Result,
+};
+use http_body_util::Full;
+use hyper::{body::Bytes, Response, StatusCode};
+use serde::Serialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use structopt::StructOpt;
+
+/// arweave compatible http mirror of stored blocks
+#[derive(Debug, StructOpt)]
+pub struct Mirror {
+    /// address serving the mirror
+    #[structopt(long, default_value = "127.0.0.1:1984")]
+    addr: SocketAddr,
+}
+
+/// json response, 404 if `value` is `None`
+fn json_response<T: Serialize>(value: Result<Option<T>>) -> Response<Full<Bytes>> {
+    match value.and_then(|value| Ok(value.map(|v| serde_json::to_vec(&v)).transpose()?)) {
+        Ok(Some(body)) => response(StatusCode::OK, "application/json", body),
+        Ok(None) => response(StatusCode::NOT_FOUND, "text/plain", "Not Found."),
+        Err(e) => {
+            log::error!("mirror failed to read the block database: {:?}", e);
+            response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "text/plain",
+                e.to_string(),
+            )
+        }
+    }
+}
+
+/// block containing transaction `id`
+fn block_of_tx(storage: &Storage, id: &str) -> Result<Option<(FirehoseBlock, usize)>> {
+    let location = match storage.lookup_tx(id)? {
+        Some(location) => location,
+        None => return Ok(None),
+    };
+
+    Ok(storage
+        .get_block(location.height)?
+        .map(|block| (block, location.position as usize))
+        .filter(|(block, position)| {
+            block.txs.get(*position).map(|tx| tx.id.to_string() == id) == Some(true)
+        }))
+}
+
+/// route requests to the stored blocks of `network`
+fn handle(storage: &Storage, network: &str, path: &str) -> Response<Full<Bytes>> {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["block", "height", height] => match height.parse::<u64>() {
+            Ok(height) => json_response(
+                storage
+                    .get_block(height)
+                    .map(|block| block.as_ref().map(Block::from)),
+            ),
+            Err(_) => response(StatusCode::BAD_REQUEST, "text/plain", "Invalid height."),
+        },
+        ["block", "hash", hash] => json_response(
+            storage
+                .get_block_by_hash(hash)
+                .map(|block| block.as_ref().map(Block::from)),
+        ),
+        ["tx", id] => json_response(
+            block_of_tx(storage, id)
+                .map(|found| found.map(|(block, position)| block.txs[position].as_returned())),
+        ),
+        ["tx", id, "data"] => match block_of_tx(storage, id) {
+            Ok(Some((block, position))) if !block.txs[position].data.is_empty() => response(
+                StatusCode::OK,
+                "text/plain",
+                block.txs[position].data.to_string(),
+            ),
+            Ok(_) => response(StatusCode::NOT_FOUND, "text/plain", "Not Found."),
+            Err(e) => json_response::<()>(Err(e)),
+        },
+        ["current_block"] => json_response(current_block(storage)),
+        ["info"] => json_response(current_block(storage).map(|block| {
+            Some(json!({
+                "network": network,
+                "height": block.as_ref().map(|block| block.height),
+                "current": block.as_ref().map(|block| block.indep_hash.to_string()),
+                "blocks": storage.len(),
+                "peers": 0,
+                "queue_length": 0,
+                "node_state_latency": 0,
+            }))
+        })),
+        _ => response(StatusCode::NOT_FOUND, "text/plain", "Not Found."),
+    }
+}
+
+/// highest stored block
+fn current_block(storage: &Storage) -> Result<Option<Block>> {
+    Ok(match storage.last_height()? {
+        Some(height) => storage.get_block(height)?.as_ref().map(Block::from),
+        None => None,
+    })
+}
+
+impl Mirror {
+    /// serve stored blocks until the server fails
+    pub async fn exec(&self, env: Env) -> Result<()> {
+        let storage = Storage::open(&env.db_path)?;
+        if storage.is_empty() {
+            log::warn!("block database {:?} is empty", storage.path());
+        }
+        // clients of the mirror would retry the missing txs forever
+        if storage.tx_index_is_empty() && storage.has_txs()? {
+            return Err(anyhow::anyhow!(
+                "transactions of {:?} are not indexed, run `thegarii lookup-tx --rebuild` first",
+                storage.path()
+            )
+            .into());
+        }
+
+        let network = env.network.name;
+        server::serve(self.addr, move |request| {
+            handle(&storage, &network, request.uri().path())
+        })
+        .await
+    }
+}
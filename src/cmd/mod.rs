@@ -2,12 +2,21 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 
 //! thegarii commands
-use crate::{Env, EnvArguments, Result};
+use crate::{Env, EnvArguments, Error, Result};
+use std::{io::IsTerminal, str::FromStr};
 use structopt::StructOpt;
+use tracing_subscriber::EnvFilter;
 
 mod console;
+mod decode;
+mod export;
 mod get;
+#[cfg(feature = "full")]
+mod lookup_tx;
+#[cfg(feature = "full")]
+mod mirror;
 mod poll;
+mod verify;
 
 #[derive(StructOpt, Debug)]
 pub enum Command {
@@ -15,8 +24,39 @@ pub enum Command {
     Get(get::Get),
     /// Poll blocks and print to stdout
     Console(console::Console),
+    /// Decode FIRE lines or protobuf payloads into JSON
+    Decode(decode::Decode),
+    /// Export a range of blocks to files
+    Export(export::Export),
     /// Dry-run random polling with time estimating
     Poll(poll::Poll),
+    /// Audit a range of blocks against every endpoint
+    Verify(verify::Verify),
+    /// Locate transactions in the index of stored blocks
+    #[cfg(feature = "full")]
+    LookupTx(lookup_tx::LookupTx),
+    /// Serve stored blocks with the Arweave http api
+    #[cfg(feature = "full")]
+    Mirror(mirror::Mirror),
+}
+
+/// format of the logs written to stderr
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!("invalid log format {}, expect text or json", s).into()),
+        }
+    }
 }
 
 #[derive(StructOpt, Debug)]
@@ -26,6 +66,10 @@ pub struct Opt {
     #[structopt(short, long)]
     pub debug: bool,
 
+    /// Format of the logs written to stderr, text or json
+    #[structopt(long, default_value = "text")]
+    pub log_format: LogFormat,
+
     #[structopt(flatten)]
     pub env: EnvArguments,
 
@@ -39,12 +83,20 @@ impl Opt {
     pub async fn exec() -> Result<()> {
         let opt = Opt::from_args();
 
-        if opt.debug {
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("thegarii"))
-                .init();
-        } else {
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
+        // logs go to stderr, stdout is reserved for the firehose logs
+        let filter = EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(if opt.debug { "thegarii" } else { "info" }));
+        let logger = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_writer(std::io::stderr);
+        match opt.log_format {
+            LogFormat::Text => logger.init(),
+            LogFormat::Json => logger
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .init(),
         }
 
         // extract env
@@ -53,9 +105,16 @@ impl Opt {
 
         // process commmands
         match opt.command {
-            Command::Get(get) => get.exec().await?,
+            Command::Get(get) => get.exec(env).await?,
             Command::Poll(poll) => poll.exec(env).await?,
             Command::Console(console) => console.exec(env).await?,
+            Command::Verify(verify) => verify.exec(env).await?,
+            Command::Decode(decode) => decode.exec().await?,
+            Command::Export(export) => export.exec(env).await?,
+            #[cfg(feature = "full")]
+            Command::LookupTx(lookup_tx) => lookup_tx.exec(env).await?,
+            #[cfg(feature = "full")]
+            Command::Mirror(mirror) => mirror.exec(env).await?,
         }
 
         Ok(())
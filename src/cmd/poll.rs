@@ -85,7 +85,9 @@ impl Poll {
 
             _blocks.sort_unstable();
             log::info!("polling blocks {:?}...", _blocks);
-            client.poll(_blocks.into_iter()).await?;
+            client
+                .poll(_blocks.into_iter(), env.batch_blocks as usize)
+                .await?;
         }
 
         // log result
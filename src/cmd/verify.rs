@@ -0,0 +1,285 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+use crate::{
+    types::{Base64Url, Block},
+    verify::{verify_tx, verify_txs},
+    Client, Env, Error, Result,
+};
+use futures::{future::join_all, stream, StreamExt};
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+use structopt::StructOpt;
+
+/// txs of a block fetched at the same time from an endpoint
+const TXS_IN_FLIGHT: usize = 10;
+
+/// divergence between endpoints or missing data, reported as a JSON line
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Finding {
+    /// the endpoint failed to return the block
+    MissingBlock {
+        height: u64,
+        endpoint: String,
+        error: String,
+    },
+    /// the endpoint returned a block of another height
+    WrongHeight {
+        height: u64,
+        endpoint: String,
+        actual: u64,
+    },
+    /// `previous_block` is not the block the endpoint returned at the
+    /// previous height
+    Discontinuity {
+        height: u64,
+        endpoint: String,
+        previous_block: String,
+        expected: String,
+    },
+    /// endpoints returned different blocks
+    ForkedBlock {
+        height: u64,
+        indep_hashes: BTreeMap<String, String>,
+    },
+    /// endpoints returned the same block with different txs
+    TxsDivergence {
+        height: u64,
+        txs: BTreeMap<String, Vec<String>>,
+    },
+    /// the endpoint failed to return a tx of the block
+    MissingTx {
+        height: u64,
+        endpoint: String,
+        id: String,
+        error: String,
+    },
+    /// txs returned by the endpoint do not match the block
+    TxRootMismatch {
+        height: u64,
+        endpoint: String,
+        reason: String,
+    },
+    /// a tx returned by the endpoint fails signature verification
+    InvalidTx {
+        height: u64,
+        endpoint: String,
+        id: String,
+        reason: String,
+    },
+}
+
+/// audit a range of blocks against every endpoint
+#[derive(Debug, StructOpt)]
+pub struct Verify {
+    /// first block of the range
+    #[structopt(short, long)]
+    start: u64,
+    /// last block of the range, included
+    #[structopt(short, long)]
+    end: u64,
+    /// fetch the txs of each block and check they hash to its tx_root
+    #[structopt(long)]
+    tx_root: bool,
+    /// fetch the txs of each block and verify their signatures and ids
+    #[structopt(long)]
+    signatures: bool,
+}
+
+impl Verify {
+    /// block at `height` from `client` and the findings of its txs
+    async fn audit_endpoint(&self, client: &Client, height: u64) -> (Option<Block>, Vec<Finding>) {
+        let endpoint = client.endpoints[0].clone();
+        let block = match client.get_block_by_height(height).await {
+            Ok(block) if block.height == height => block,
+            Ok(block) => {
+                let finding = Finding::WrongHeight {
+                    height,
+                    endpoint,
+                    actual: block.height,
+                };
+                return (None, vec![finding]);
+            }
+            Err(e) => {
+                let finding = Finding::MissingBlock {
+                    height,
+                    endpoint,
+                    error: e.to_string(),
+                };
+                return (None, vec![finding]);
+            }
+        };
+        if !self.tx_root && !self.signatures {
+            return (Some(block), vec![]);
+        }
+
+        let ids = block
+            .txs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let fetched = stream::iter(&ids)
+            .map(|id| client.get_tx_by_id(id))
+            .buffered(TXS_IN_FLIGHT)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut findings = vec![];
+        let mut txs = vec![];
+        for (id, tx) in ids.into_iter().zip(fetched) {
+            match tx {
+                Ok(tx) => txs.push(tx),
+                Err(e) => findings.push(Finding::MissingTx {
+                    height,
+                    endpoint: endpoint.clone(),
+                    id,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if self.signatures {
+            for tx in &txs {
+                if let Err(Error::InvalidTx { id, reason }) = verify_tx(tx) {
+                    findings.push(Finding::InvalidTx {
+                        height,
+                        endpoint: endpoint.clone(),
+                        id,
+                        reason,
+                    });
+                }
+            }
+        }
+        // missing txs are reported already
+        if self.tx_root && txs.len() == block.txs.len() {
+            if let Err(e) = verify_txs(&block, &txs, client.network()) {
+                findings.push(Finding::TxRootMismatch {
+                    height,
+                    endpoint,
+                    reason: e.to_string(),
+                });
+            }
+        }
+
+        (Some(block), findings)
+    }
+
+    /// blocks at `height` from every client and the findings about them
+    async fn audit(&self, clients: &[Client], height: u64) -> (Vec<Option<Block>>, Vec<Finding>) {
+        let (blocks, findings): (Vec<_>, Vec<_>) = join_all(
+            clients
+                .iter()
+                .map(|client| self.audit_endpoint(client, height)),
+        )
+        .await
+        .into_iter()
+        .unzip();
+        let mut findings = findings.into_iter().flatten().collect::<Vec<_>>();
+
+        let returned = || {
+            clients
+                .iter()
+                .zip(&blocks)
+                .filter_map(|(client, block)| Some((client.endpoints[0].clone(), block.as_ref()?)))
+        };
+        let indep_hashes = returned()
+            .map(|(endpoint, block)| (endpoint, block.indep_hash.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        let txs = returned()
+            .map(|(endpoint, block)| {
+                (
+                    endpoint,
+                    block
+                        .txs
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        if indep_hashes
+            .values()
+            .any(|h| Some(h) != indep_hashes.values().next())
+        {
+            findings.push(Finding::ForkedBlock {
+                height,
+                indep_hashes,
+            });
+        } else if txs.values().any(|t| Some(t) != txs.values().next()) {
+            findings.push(Finding::TxsDivergence { height, txs });
+        }
+
+        (blocks, findings)
+    }
+
+    pub async fn exec(&self, env: Env) -> Result<()> {
+        if self.start > self.end {
+            return Err(Error::InvalidRange);
+        }
+
+        // one client per endpoint, the block database is not involved
+        let clients = env
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                Ok(Client::new(
+                    vec![endpoint.clone()],
+                    Duration::from_millis(env.timeout),
+                    env.retry,
+                    env.network.clone(),
+                )?
+                .with_retry_delay(Duration::from_millis(env.retry_delay)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let clients = &clients;
+        let mut audits = stream::iter(self.start..=self.end)
+            .map(|height| async move { (height, self.audit(clients, height).await) })
+            .buffered(env.batch_blocks.max(1) as usize);
+
+        // blocks of the previous height per endpoint
+        let mut previous: Vec<Option<Base64Url>> = vec![None; clients.len()];
+        let mut found = 0;
+        while let Some((height, (blocks, mut findings))) = audits.next().await {
+            for ((client, block), previous) in clients.iter().zip(&blocks).zip(&mut previous) {
+                if let (Some(block), Some(expected)) = (block, &previous) {
+                    if &block.previous_block != expected {
+                        findings.push(Finding::Discontinuity {
+                            height,
+                            endpoint: client.endpoints[0].clone(),
+                            previous_block: block.previous_block.to_string(),
+                            expected: expected.to_string(),
+                        });
+                    }
+                }
+                *previous = block.as_ref().map(|block| block.indep_hash.clone());
+            }
+
+            for finding in &findings {
+                println!("{}", serde_json::to_string(finding)?);
+            }
+            found += findings.len();
+            log::debug!("verified block {}, {} findings", height, findings.len());
+        }
+
+        log::info!(
+            "verified blocks {}..={} against {} endpoints, {} findings",
+            self.start,
+            self.end,
+            clients.len(),
+            found
+        );
+        if found > 0 {
+            return Err(anyhow::anyhow!(
+                "{} findings in blocks {}..={}",
+                found,
+                self.start,
+                self.end
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! adaptive fetch concurrency
+//!
+//! an AIMD (additive increase, multiplicative decrease) controller deciding
+//! how many blocks can be fetched at the same time, the limit grows by one
+//! block per window of healthy blocks and is halved on throttling, timeouts
+//! and server errors
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// request latency above `TOLERANCE` times the best observed latency is unhealthy
+const TOLERANCE: f64 = 2.0;
+/// weight of the latest sample in the latency moving average
+const SMOOTHING: f64 = 0.2;
+/// minimal interval between two decreases
+const MIN_COOLDOWN: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct State {
+    limit: usize,
+    healthy_blocks: usize,
+    latency: Option<f64>,
+    best_latency: Option<f64>,
+    last_decrease: Option<Instant>,
+}
+
+/// AIMD concurrency controller
+///
+/// ```rust
+/// use thegarii::concurrency::Concurrency;
+///
+/// let concurrency = Concurrency::new(4, 2, 5);
+/// (0..4).for_each(|_| concurrency.on_block());
+/// assert_eq!(concurrency.limit(), 5);
+///
+/// // never exceeds the upper bound
+/// (0..10).for_each(|_| concurrency.on_block());
+/// assert_eq!(concurrency.limit(), 5);
+///
+/// // halved on errors, never below the lower bound
+/// concurrency.on_error();
+/// assert_eq!(concurrency.limit(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Concurrency {
+    min: usize,
+    max: usize,
+    state: Mutex<State>,
+}
+
+impl Concurrency {
+    /// new controller starting from `initial` in flight blocks, bounded by `min` and `max`
+    pub fn new(initial: usize, min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+
+        Self {
+            min,
+            max,
+            state: Mutex::new(State {
+                limit: initial.clamp(min, max),
+                healthy_blocks: 0,
+                latency: None,
+                best_latency: None,
+                last_decrease: None,
+            }),
+        }
+    }
+
+    /// current limit of in flight blocks
+    pub fn limit(&self) -> usize {
+        self.state.lock().expect("concurrency state poisoned").limit
+    }
+
+    /// record the latency of a successful request
+    pub fn on_response(&self, latency: Duration) {
+        let sample = latency.as_secs_f64() * 1000.0;
+        let mut state = self.state.lock().expect("concurrency state poisoned");
+
+        let latency = match state.latency {
+            Some(avg) => avg + SMOOTHING * (sample - avg),
+            None => sample,
+        };
+        state.latency = Some(latency);
+
+        // let the best latency drift up slowly, so one lucky sample
+        // won't keep the controller unhealthy forever
+        state.best_latency = Some(match state.best_latency {
+            Some(best) if best <= latency => best + (latency - best) * SMOOTHING / 10.0,
+            _ => latency,
+        });
+    }
+
+    /// record a throttled, timed out or failed request
+    pub fn on_error(&self) {
+        let mut state = self.state.lock().expect("concurrency state poisoned");
+
+        let cooldown = state
+            .latency
+            .map(|ms| Duration::from_millis(ms as u64).max(MIN_COOLDOWN))
+            .unwrap_or(MIN_COOLDOWN);
+        if let Some(last) = state.last_decrease {
+            if last.elapsed() < cooldown {
+                return;
+            }
+        }
+
+        let limit = (state.limit / 2).max(self.min);
+        if limit != state.limit {
+            log::info!(
+                "decreasing fetch concurrency from {} to {} blocks",
+                state.limit,
+                limit
+            );
+        }
+
+        state.limit = limit;
+        state.healthy_blocks = 0;
+        state.last_decrease = Some(Instant::now());
+    }
+
+    /// record a fetched block, increases the limit once a full window of
+    /// blocks has been fetched with healthy latency
+    pub fn on_block(&self) {
+        let mut state = self.state.lock().expect("concurrency state poisoned");
+
+        let healthy = match (state.latency, state.best_latency) {
+            (Some(latency), Some(best)) => latency <= best * TOLERANCE,
+            _ => true,
+        };
+        if !healthy {
+            state.healthy_blocks = 0;
+            return;
+        }
+
+        state.healthy_blocks += 1;
+        if state.healthy_blocks >= state.limit && state.limit < self.max {
+            state.limit += 1;
+            state.healthy_blocks = 0;
+            log::info!("increasing fetch concurrency to {} blocks", state.limit);
+        }
+    }
+}
@@ -2,8 +2,53 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 
 //! encoding utils
-use serde::de::{self, Deserialize, Deserializer};
-use serde_json::value::Value;
+use crate::Error;
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde_json::{error::Category, value::Value};
+
+/// parse the JSON response of `path`, invalid values are reported with the
+/// path of their field and the height of their block, if any
+///
+/// ```rust
+/// use thegarii::{encoding, types::Block};
+///
+/// let json = include_str!("../res/block_height_100.json").replace(r#""hash": ""#, r#""hash": "!"#);
+/// let error = encoding::from_slice::<Block>(json.as_bytes(), "block/height/100").unwrap_err();
+/// assert!(error
+///     .to_string()
+///     .starts_with(r#"invalid hash of block 100 in block/height/100: invalid base64url "!AAAAANsE"#));
+///
+/// // blocks fetched by hash are named by their height too
+/// let json = include_str!("../res/block_height_100.json").replace("7Boxcx", "!7Boxcx");
+/// let error = encoding::from_slice::<Block>(json.as_bytes(), "block/hash/h").unwrap_err();
+/// assert!(error
+///     .to_string()
+///     .starts_with(r#"invalid txs[0] of block 100 in block/hash/h: invalid base64url "!7Boxcx"#));
+/// ```
+pub fn from_slice<T: DeserializeOwned>(json: &[u8], path: &str) -> crate::Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let field = e.path().to_string();
+        let source = e.into_inner();
+        // syntax errors and missing fields at the root have no field to name
+        if source.classify() != Category::Data || field == "." {
+            return source.into();
+        }
+
+        let height = serde_json::from_slice::<Value>(json)
+            .ok()
+            .and_then(|value| value.get("height")?.as_u64());
+        Error::InvalidField {
+            field,
+            height,
+            path: path.into(),
+            source,
+        }
+    })?;
+
+    deserializer.end()?;
+    Ok(value)
+}
 
 /// parse number or string to string
 pub fn number_or_string<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -22,22 +67,43 @@ where
     })
 }
 
-/// parse number or string to string
-pub fn option_number_or_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+/// parse `{}` or `null` to `None`
+pub fn empty_object_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
+    T: de::DeserializeOwned,
 {
-    if let Ok(v) = Value::deserialize(deserializer) {
-        return Ok(match v {
-            Value::Number(n) => Some(n.to_string()),
-            Value::String(s) => Some(s),
-            _ => {
-                return Err(de::Error::custom(
-                    "invalid diff type, expect number or string",
-                ))
-            }
-        });
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::Object(o) if o.is_empty() => Ok(None),
+        v => serde_json::from_value(v)
+            .map(Some)
+            .map_err(de::Error::custom),
+    }
+}
+
+/// `Base64Url` encoded as `unclaimed` if empty, i.e. the reward address of
+/// blocks without miner
+pub mod unclaimed {
+    use crate::types::Base64Url;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    const UNCLAIMED: &str = "unclaimed";
+
+    pub fn serialize<S: Serializer>(bytes: &Base64Url, serializer: S) -> Result<S::Ok, S::Error> {
+        if bytes.is_empty() {
+            serializer.serialize_str(UNCLAIMED)
+        } else {
+            bytes.serialize(serializer)
+        }
     }
 
-    Ok(None)
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Base64Url, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if s == UNCLAIMED {
+            return Ok(Base64Url::default());
+        }
+
+        Base64Url::deserialize(serde::de::value::StrDeserializer::new(&s))
+    }
 }
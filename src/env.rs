@@ -2,7 +2,14 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 
 //! App envorionments
-use crate::Result;
+#[cfg(feature = "full")]
+use crate::Error;
+use crate::{
+    client::TxFailurePolicy,
+    network::Network,
+    verify::{TxRootPolicy, VerifyPolicy},
+    Result,
+};
 use std::{env, path::PathBuf};
 use structopt::StructOpt;
 
@@ -12,21 +19,52 @@ const ENDPOINTS: &str = "ENDPOINTS";
 const DEFAULT_ENDPOINTS: &str = "https://arweave.net";
 const BATCH_BLOCKS: &str = "BATCH_BLOCKS";
 const DEFAULT_BATCH_BLOCKS: u16 = 50;
+const MIN_BATCH_BLOCKS: &str = "MIN_BATCH_BLOCKS";
+const DEFAULT_MIN_BATCH_BLOCKS: u16 = 1;
+const MAX_BATCH_BLOCKS: &str = "MAX_BATCH_BLOCKS";
+const DEFAULT_MAX_BATCH_BLOCKS: u16 = 200;
+const FETCH_DATA: &str = "FETCH_DATA";
+const DEFAULT_FETCH_DATA: bool = false;
+const DATA_SIZE_CAP: &str = "DATA_SIZE_CAP";
+const DEFAULT_DATA_SIZE_CAP: u64 = 10 * 1024 * 1024;
+const DATA_CONTENT_TYPES: &str = "DATA_CONTENT_TYPES";
+const MEMORY_BUDGET: &str = "MEMORY_BUDGET";
+const NETWORK: &str = "NETWORK";
+const DEFAULT_MEMORY_BUDGET: u64 = 512 * 1024 * 1024;
 const RETRY: &str = "RETRY";
 const DEFAULT_RETRY: u8 = 10;
+const RETRY_DELAY: &str = "RETRY_DELAY";
+const DEFAULT_RETRY_DELAY: u64 = 10_000;
 const CONFIRMS: &str = "CONFIRMS";
 const DEFAULT_CONFIRMS: u64 = 20;
+const TX_ROUNDS: &str = "TX_ROUNDS";
+const DEFAULT_TX_ROUNDS: u8 = 3;
+const TX_FAILURE: &str = "TX_FAILURE";
+const DEFAULT_TX_FAILURE: TxFailurePolicy = TxFailurePolicy::Fail;
+const TX_ROOT_CHECK: &str = "TX_ROOT_CHECK";
+const DEFAULT_TX_ROOT_CHECK: TxRootPolicy = TxRootPolicy::Fail;
+const VERIFY_TXS: &str = "VERIFY_TXS";
 const TIMEOUT: &str = "TIMEOUT";
 const DEFAULT_TIMEOUT: u64 = 120_000;
+#[cfg(feature = "full")]
+const DB_PATH: &str = "DB_PATH";
+#[cfg(feature = "full")]
+const INDEX_TXS: &str = "INDEX_TXS";
 const PTR_FILE: &str = "PTR_FILE";
 const DEFAULT_PTR_FILE: &str = "./arweave.ptr";
 
 /// env arguments for CLI
 #[derive(Debug, StructOpt)]
 pub struct EnvArguments {
-    /// how many blocks polling at one time
-    #[structopt(short = "B", long, default_value = "20")]
+    /// how many blocks polling at one time when starting
+    #[structopt(short = "B", long, default_value = "50")]
     pub batch_blocks: u16,
+    /// lower bound of blocks polling at one time
+    #[structopt(long, default_value = "1")]
+    pub min_batch_blocks: u16,
+    /// upper bound of blocks polling at one time
+    #[structopt(long, default_value = "200")]
+    pub max_batch_blocks: u16,
     /// time cost for producing a new block in arweave
     #[structopt(short, long, default_value = "60000")]
     pub block_time: u64,
@@ -36,34 +74,102 @@ pub struct EnvArguments {
     /// client endpoints
     #[structopt(short, long, default_value = "https://arweave.net/")]
     pub endpoints: Vec<String>,
+    /// fetch the payload of format 2 transactions into firehose blocks
+    #[structopt(long)]
+    pub fetch_data: bool,
+    /// payloads of format 2 transactions bigger than this size in bytes are not fetched
+    #[structopt(long, default_value = "10485760")]
+    pub data_size_cap: u64,
+    /// only fetch payloads with these content types, i.e. `image/png,image/*`
+    #[structopt(long, require_delimiter = true)]
+    pub data_content_types: Vec<String>,
+    /// bytes of fetched blocks buffered in memory before pausing new fetches
+    #[structopt(long, default_value = "536870912")]
+    pub memory_budget: u64,
+    /// fork schedule, mainnet, local or the path of a JSON schedule [default: mainnet]
+    #[structopt(long)]
+    pub network: Option<Network>,
+    /// block database path, `$APP_DATA/thegarii/db` by default
+    #[cfg(feature = "full")]
+    #[structopt(long)]
+    pub db_path: Option<PathBuf>,
+    /// index transaction ids of stored blocks for `lookup-tx`
+    #[cfg(feature = "full")]
+    #[structopt(long)]
+    pub index_txs: bool,
     /// block pointer path
     #[structopt(short, long, default_value = "./arweave.ptr")]
     pub ptr_file: PathBuf,
     /// retry times when failed on http requests
     #[structopt(short, long, default_value = "10")]
     pub retry: u8,
+    /// milliseconds before the first retry of a failed http request, doubled on each retry
+    #[structopt(long, default_value = "10000")]
+    pub retry_delay: u64,
     /// timeout of http requests
     #[structopt(short, long, default_value = "120000")]
     pub timeout: u64,
+    /// rounds of fetching the missing transactions of a block
+    #[structopt(long, default_value = "3")]
+    pub tx_rounds: u8,
+    /// what to do once transactions are still missing after all rounds, fail or wait
+    #[structopt(long, default_value = "fail")]
+    pub tx_failure: TxFailurePolicy,
+    /// check transactions hash to the tx_root of their block, fail, warn or off [default: fail]
+    #[structopt(long)]
+    pub tx_root_check: Option<TxRootPolicy>,
+    /// verify signatures and ids of transactions, reject, warn or annotate invalid ones
+    #[structopt(long)]
+    pub verify_txs: Option<VerifyPolicy>,
 }
 
 /// environments
 #[derive(Clone, Debug)]
 pub struct Env {
-    /// how many blocks polling at one time
+    /// how many blocks polling at one time when starting
     pub batch_blocks: u16,
+    /// lower bound of blocks polling at one time
+    pub min_batch_blocks: u16,
+    /// upper bound of blocks polling at one time
+    pub max_batch_blocks: u16,
     /// time cost for producing a new block in arweave
     pub block_time: u64,
     /// safe blocks against to reorg in polling
     pub confirms: u64,
     /// client endpoints
     pub endpoints: Vec<String>,
+    /// fetch the payload of format 2 transactions into firehose blocks
+    pub fetch_data: bool,
+    /// payloads of format 2 transactions bigger than this size in bytes are not fetched
+    pub data_size_cap: u64,
+    /// only fetch payloads with these content types, fetch all if empty
+    pub data_content_types: Vec<String>,
+    /// bytes of fetched blocks buffered in memory before pausing new fetches
+    pub memory_budget: u64,
+    /// fork schedule of the network
+    pub network: Network,
+    /// block database path
+    #[cfg(feature = "full")]
+    pub db_path: PathBuf,
+    /// index transaction ids of stored blocks
+    #[cfg(feature = "full")]
+    pub index_txs: bool,
     /// block pointer path
     pub ptr_file: PathBuf,
     /// retry times when failed on http requests
     pub retry: u8,
+    /// milliseconds before the first retry of a failed http request
+    pub retry_delay: u64,
     /// timeout of http requests
     pub timeout: u64,
+    /// rounds of fetching the missing transactions of a block
+    pub tx_rounds: u8,
+    /// what to do once transactions are still missing after all rounds
+    pub tx_failure: TxFailurePolicy,
+    /// what to do with transactions not hashing to the tx_root of their block
+    pub tx_root_check: TxRootPolicy,
+    /// what to do with transactions failing verification, not verified if unset
+    pub verify_txs: Option<VerifyPolicy>,
 }
 
 impl Env {
@@ -85,6 +191,30 @@ impl Env {
         Ok(raw_endpoints.split(',').map(|e| e.to_string()).collect())
     }
 
+    /// get $FETCH_DATA from env or use $DEFAULT_FETCH_DATA
+    pub fn fetch_data() -> Result<bool> {
+        Ok(match env::var(FETCH_DATA) {
+            Ok(fetch) => fetch.parse()?,
+            Err(_) => DEFAULT_FETCH_DATA,
+        })
+    }
+
+    /// get $DATA_SIZE_CAP from env or use $DEFAULT_DATA_SIZE_CAP
+    pub fn data_size_cap() -> Result<u64> {
+        Ok(match env::var(DATA_SIZE_CAP) {
+            Ok(cap) => cap.parse()?,
+            Err(_) => DEFAULT_DATA_SIZE_CAP,
+        })
+    }
+
+    /// get $DATA_CONTENT_TYPES from env or accept all content types
+    pub fn data_content_types() -> Result<Vec<String>> {
+        Ok(match env::var(DATA_CONTENT_TYPES) {
+            Ok(types) => types.split(',').map(|t| t.to_string()).collect(),
+            Err(_) => vec![],
+        })
+    }
+
     /// get $BATCH_BLOCKS from env or use $DEFAULT_BATCH_BLOCKS
     pub fn batch_blocks() -> Result<u16> {
         Ok(match env::var(BATCH_BLOCKS) {
@@ -93,6 +223,51 @@ impl Env {
         })
     }
 
+    /// get $MIN_BATCH_BLOCKS from env or use $DEFAULT_MIN_BATCH_BLOCKS
+    pub fn min_batch_blocks() -> Result<u16> {
+        Ok(match env::var(MIN_BATCH_BLOCKS) {
+            Ok(blocks) => blocks.parse()?,
+            Err(_) => DEFAULT_MIN_BATCH_BLOCKS,
+        })
+    }
+
+    /// get $MAX_BATCH_BLOCKS from env or use $DEFAULT_MAX_BATCH_BLOCKS
+    pub fn max_batch_blocks() -> Result<u16> {
+        Ok(match env::var(MAX_BATCH_BLOCKS) {
+            Ok(blocks) => blocks.parse()?,
+            Err(_) => DEFAULT_MAX_BATCH_BLOCKS,
+        })
+    }
+
+    /// get $MEMORY_BUDGET from env or use $DEFAULT_MEMORY_BUDGET
+    pub fn memory_budget() -> Result<u64> {
+        Ok(match env::var(MEMORY_BUDGET) {
+            Ok(bytes) => bytes.parse()?,
+            Err(_) => DEFAULT_MEMORY_BUDGET,
+        })
+    }
+
+    /// get $DB_PATH from env or use `$APP_DATA/thegarii/db`
+    #[cfg(feature = "full")]
+    pub fn db_path() -> Result<PathBuf> {
+        Ok(match env::var(DB_PATH) {
+            Ok(path) => path.into(),
+            Err(_) => dirs::data_dir()
+                .ok_or(Error::NoDataDirectory)?
+                .join("thegarii")
+                .join("db"),
+        })
+    }
+
+    /// get $INDEX_TXS from env or don't index transactions
+    #[cfg(feature = "full")]
+    pub fn index_txs() -> Result<bool> {
+        Ok(match env::var(INDEX_TXS) {
+            Ok(index) => index.parse()?,
+            Err(_) => false,
+        })
+    }
+
     /// get $PTR_FILE from env or use DEFAULT_PTR_FILE
     pub fn ptr_file() -> Result<PathBuf> {
         Ok(match env::var(PTR_FILE) {
@@ -109,6 +284,14 @@ impl Env {
         })
     }
 
+    /// get $RETRY_DELAY from env or use $DEFAULT_RETRY_DELAY
+    pub fn retry_delay() -> Result<u64> {
+        Ok(match env::var(RETRY_DELAY) {
+            Ok(ms) => ms.parse()?,
+            Err(_) => DEFAULT_RETRY_DELAY,
+        })
+    }
+
     /// get $CONFIRMS from env or use $DEFAULT_CONFIRMS
     pub fn confirms() -> Result<u64> {
         Ok(match env::var(CONFIRMS) {
@@ -125,16 +308,72 @@ impl Env {
         })
     }
 
+    /// get $TX_ROUNDS from env or use $DEFAULT_TX_ROUNDS
+    pub fn tx_rounds() -> Result<u8> {
+        Ok(match env::var(TX_ROUNDS) {
+            Ok(rounds) => rounds.parse()?,
+            Err(_) => DEFAULT_TX_ROUNDS,
+        })
+    }
+
+    /// get $TX_FAILURE from env or use $DEFAULT_TX_FAILURE
+    pub fn tx_failure() -> Result<TxFailurePolicy> {
+        Ok(match env::var(TX_FAILURE) {
+            Ok(policy) => policy.parse()?,
+            Err(_) => DEFAULT_TX_FAILURE,
+        })
+    }
+
+    /// get $TX_ROOT_CHECK from env or use $DEFAULT_TX_ROOT_CHECK
+    pub fn tx_root_check() -> Result<TxRootPolicy> {
+        Ok(match env::var(TX_ROOT_CHECK) {
+            Ok(policy) => policy.parse()?,
+            Err(_) => DEFAULT_TX_ROOT_CHECK,
+        })
+    }
+
+    /// get $VERIFY_TXS from env, transactions are not verified if unset
+    pub fn verify_txs() -> Result<Option<VerifyPolicy>> {
+        Ok(match env::var(VERIFY_TXS) {
+            Ok(policy) => Some(policy.parse()?),
+            Err(_) => None,
+        })
+    }
+
+    /// get $NETWORK from env or use mainnet
+    pub fn network() -> Result<Network> {
+        match env::var(NETWORK) {
+            Ok(network) => network.parse(),
+            Err(_) => Ok(Network::mainnet()),
+        }
+    }
+
     /// new environments
     pub fn new() -> Result<Self> {
         Ok(Self {
             batch_blocks: Self::batch_blocks()?,
+            min_batch_blocks: Self::min_batch_blocks()?,
+            max_batch_blocks: Self::max_batch_blocks()?,
             block_time: Self::block_time()?,
             confirms: Self::confirms()?,
             endpoints: Self::endpoints()?,
+            fetch_data: Self::fetch_data()?,
+            data_size_cap: Self::data_size_cap()?,
+            data_content_types: Self::data_content_types()?,
+            memory_budget: Self::memory_budget()?,
+            network: Self::network()?,
+            #[cfg(feature = "full")]
+            db_path: Self::db_path()?,
+            #[cfg(feature = "full")]
+            index_txs: Self::index_txs()?,
             ptr_file: Self::ptr_file()?,
             retry: Self::retry()?,
+            retry_delay: Self::retry_delay()?,
             timeout: Self::timeout()?,
+            tx_rounds: Self::tx_rounds()?,
+            tx_failure: Self::tx_failure()?,
+            tx_root_check: Self::tx_root_check()?,
+            verify_txs: Self::verify_txs()?,
         })
     }
 
@@ -142,6 +381,8 @@ impl Env {
     pub fn from_args(args: EnvArguments) -> Result<Self> {
         Ok(Self {
             batch_blocks: args.batch_blocks,
+            min_batch_blocks: args.min_batch_blocks,
+            max_batch_blocks: args.max_batch_blocks,
             block_time: args.block_time,
             confirms: args.confirms,
             endpoints: if args.endpoints.is_empty() {
@@ -149,9 +390,39 @@ impl Env {
             } else {
                 args.endpoints
             },
+            fetch_data: args.fetch_data,
+            data_size_cap: args.data_size_cap,
+            data_content_types: if args.data_content_types.is_empty() {
+                Self::data_content_types()?
+            } else {
+                args.data_content_types
+            },
+            memory_budget: args.memory_budget,
+            network: match args.network {
+                Some(network) => network,
+                None => Self::network()?,
+            },
+            #[cfg(feature = "full")]
+            db_path: match args.db_path {
+                Some(path) => path,
+                None => Self::db_path()?,
+            },
+            #[cfg(feature = "full")]
+            index_txs: args.index_txs,
             ptr_file: args.ptr_file,
             retry: args.retry,
+            retry_delay: args.retry_delay,
             timeout: args.timeout,
+            tx_rounds: args.tx_rounds,
+            tx_failure: args.tx_failure,
+            tx_root_check: match args.tx_root_check {
+                Some(policy) => policy,
+                None => Self::tx_root_check()?,
+            },
+            verify_txs: match args.verify_txs {
+                Some(policy) => Some(policy),
+                None => Self::verify_txs()?,
+            },
         })
     }
 
@@ -168,18 +439,53 @@ impl Env {
         self
     }
 
+    /// index transaction ids of stored blocks
+    #[cfg(feature = "full")]
+    pub fn with_tx_index(&mut self, index_txs: bool) -> &mut Self {
+        self.index_txs = index_txs;
+        self
+    }
+
     /// set endpoints
     pub fn with_endpoints(&mut self, endpoints: Vec<String>) -> &mut Self {
         self.endpoints = endpoints;
         self
     }
 
+    /// fetch payloads of format 2 transactions up to `size_cap` bytes with
+    /// `content_types`, all content types if empty
+    pub fn with_data(&mut self, size_cap: u64, content_types: Vec<String>) -> &mut Self {
+        self.fetch_data = true;
+        self.data_size_cap = size_cap;
+        self.data_content_types = content_types;
+        self
+    }
+
+    /// set fork schedule
+    pub fn with_network(&mut self, network: Network) -> &mut Self {
+        self.network = network;
+        self
+    }
+
     /// set polling batch blocks
     pub fn with_batch_blocks(&mut self, batch_blocks: u16) -> &mut Self {
         self.batch_blocks = batch_blocks;
         self
     }
 
+    /// set bounds of polling batch blocks
+    pub fn with_batch_bounds(&mut self, min: u16, max: u16) -> &mut Self {
+        self.min_batch_blocks = min;
+        self.max_batch_blocks = max;
+        self
+    }
+
+    /// set memory budget of buffered blocks
+    pub fn with_memory_budget(&mut self, memory_budget: u64) -> &mut Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
     /// set polling safe blocks
     pub fn with_confirms(&mut self, confirms: u64) -> &mut Self {
         self.confirms = confirms;
@@ -197,4 +503,30 @@ impl Env {
         self.retry = retry;
         self
     }
+
+    /// set milliseconds before the first retry of a failed http request
+    pub fn with_retry_delay(&mut self, retry_delay: u64) -> &mut Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// set rounds of fetching the missing transactions of a block
+    pub fn with_tx_rounds(&mut self, rounds: u8, policy: TxFailurePolicy) -> &mut Self {
+        self.tx_rounds = rounds;
+        self.tx_failure = policy;
+        self
+    }
+
+    /// what to do with transactions not hashing to the tx_root of their block
+    pub fn with_tx_root_check(&mut self, policy: TxRootPolicy) -> &mut Self {
+        self.tx_root_check = policy;
+        self
+    }
+
+    /// verify transactions of fetched blocks, `policy` tells what to do with
+    /// invalid ones
+    pub fn with_verify_txs(&mut self, policy: VerifyPolicy) -> &mut Self {
+        self.verify_txs = Some(policy);
+        self
+    }
 }
@@ -0,0 +1,242 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! transaction filters
+//!
+//! filters are boolean expressions over transaction tags, owner and target
+//!
+//! ```text
+//! expr := expr or expr | expr and expr | not expr | ( expr ) | atom
+//! atom := tag:<name> | tag:<name>=<value> | owner:<address> | target:<address>
+//! ```
+//!
+//! `and` binds tighter than `or`, keywords are case insensitive, tag names and
+//! values are matched against their utf-8 decoded content and may be quoted
+//! with `"` if they contain spaces or parentheses.
+use crate::{
+    types::{FirehoseBlock, Transaction},
+    Error, Result,
+};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+/// transaction filter
+///
+/// ```rust
+/// use thegarii::{filter::Filter, types::{Tag, Transaction}};
+///
+/// let mut tx: Transaction = serde_json::from_str(include_str!("../res/tx.json")).unwrap();
+/// tx.tags = vec![Tag {
+///     name: b"Content-Type".to_vec().into(),
+///     value: b"image/png".to_vec().into(),
+/// }];
+///
+/// let filter: Filter = r#"tag:App-Name or tag:Content-Type="image/png""#.parse().unwrap();
+/// assert!(filter.matches(&tx));
+///
+/// let filter: Filter = "tag:Content-Type=image/png and not owner:unknown".parse().unwrap();
+/// assert!(filter.matches(&tx));
+///
+/// let filter: Filter = "not (tag:Content-Type or target:unknown)".parse().unwrap();
+/// assert!(!filter.matches(&tx));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// tag with name, and value if any
+    Tag { name: String, value: Option<String> },
+    /// owner address or public key
+    Owner(String),
+    /// target address
+    Target(String),
+    /// both filters match
+    And(Box<Filter>, Box<Filter>),
+    /// any of the filters matches
+    Or(Box<Filter>, Box<Filter>),
+    /// the filter doesn't match
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// if the transaction matches this filter
+    pub fn matches(&self, tx: &Transaction) -> bool {
+        match self {
+            Self::Tag { name, value } => tx.tags.iter().any(|tag| {
+                tag.decoded_name().as_ref() == Some(name)
+                    && value
+                        .as_ref()
+                        .map(|value| tag.decoded_value().as_ref() == Some(value))
+                        .unwrap_or(true)
+            }),
+            Self::Owner(owner) => tx.owner.to_string() == *owner || address(&tx.owner) == *owner,
+            Self::Target(target) => tx.target.to_string() == *target,
+            Self::And(lhs, rhs) => lhs.matches(tx) && rhs.matches(tx),
+            Self::Or(lhs, rhs) => lhs.matches(tx) || rhs.matches(tx),
+            Self::Not(filter) => !filter.matches(tx),
+        }
+    }
+
+    /// drop transactions not matching this filter and mark the block as filtered
+    pub fn apply(&self, block: &mut FirehoseBlock) {
+        block.txs.retain(|tx| self.matches(tx));
+        block.filtered = true;
+    }
+}
+
+/// wallet address of a public key
+fn address(owner: &[u8]) -> String {
+    base64_url::encode(&Sha256::digest(owner))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+/// split expression into parentheses and words, words may be quoted
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.peek().copied() {
+        match c {
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(c) = chars.peek().copied() {
+                    match c {
+                        '"' => quoted = !quoted,
+                        '(' | ')' if !quoted => break,
+                        c if c.is_whitespace() && !quoted => break,
+                        c => word.push(c),
+                    }
+                    chars.next();
+                }
+
+                if quoted {
+                    return Err(Error::InvalidFilter(format!("unclosed quote in {}", s)));
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// recursive descent parser over tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut filter = self.and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut filter = self.unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(filter)
+                    }
+                    _ => Err(Error::InvalidFilter("missing closing parenthesis".into())),
+                }
+            }
+            Some(Token::Word(word)) => atom(&word),
+            Some(Token::Close) => Err(Error::InvalidFilter(
+                "unexpected closing parenthesis".into(),
+            )),
+            None => Err(Error::InvalidFilter("unexpected end of filter".into())),
+        }
+    }
+}
+
+/// parse `tag:`, `owner:` and `target:` conditions
+fn atom(word: &str) -> Result<Filter> {
+    let (kind, rest) = word
+        .split_once(':')
+        .ok_or_else(|| Error::InvalidFilter(format!("invalid condition {}", word)))?;
+
+    match kind.to_ascii_lowercase().as_str() {
+        "tag" => Ok(match rest.split_once('=') {
+            Some((name, value)) => Filter::Tag {
+                name: name.into(),
+                value: Some(value.into()),
+            },
+            None => Filter::Tag {
+                name: rest.into(),
+                value: None,
+            },
+        }),
+        "owner" => Ok(Filter::Owner(rest.into())),
+        "target" => Ok(Filter::Target(rest.into())),
+        _ => Err(Error::InvalidFilter(format!(
+            "invalid condition {}, expect tag:, owner: or target:",
+            word
+        ))),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+
+        let filter = parser.or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(Error::InvalidFilter(format!(
+                "unexpected trailing tokens in {}",
+                s
+            )));
+        }
+
+        Ok(filter)
+    }
+}
@@ -0,0 +1,354 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! firehose logs
+//!
+//! ```text
+//! FIRE INIT <VERSION> <BLOCK_TYPE_URL>
+//! FIRE BLOCK <BLOCK_NUM> <BLOCK_HASH> <PARENT_NUM> <PARENT_HASH> <LIB> <TIMESTAMP> <ENCODED>
+//! ```
+use crate::{
+    network::Network,
+    pb::{self, BigInt},
+    types::{FirehoseBlock, U256},
+    Error, Result,
+};
+use base64::{engine::general_purpose, Engine as _};
+use prost::Message;
+use serde_json::{json, Value};
+
+/// firehose init log
+pub const INIT: &str = "FIRE INIT 1.0 sf.arweave.type.v1.Block";
+
+/// firehose block log of `block` on `network`, `LIB` is `confirms` blocks
+/// behind it and the payload is trimmed if `quiet`
+///
+/// ```rust
+/// use thegarii::{firehose, network::Network, types::{Block, FirehoseBlock}};
+///
+/// let block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+/// let line = firehose::block_line(FirehoseBlock::from(block), &Network::mainnet(), 20, true).unwrap();
+///
+/// let fields = line.split(' ').collect::<Vec<_>>();
+/// assert_eq!(fields[..3], ["FIRE", "BLOCK", "100"]);
+/// assert_eq!(fields[4], "99");
+/// assert_eq!(fields[6], "80");
+/// assert_eq!(fields[8], "<trimmed>");
+/// ```
+pub fn block_line(
+    b: FirehoseBlock,
+    network: &Network,
+    confirms: u64,
+    quiet: bool,
+) -> Result<String> {
+    let block_num = b.height;
+    let block_hash = hex::encode(&b.indep_hash[..]);
+    let parent_hash = hex::encode(&b.previous_block[..]);
+    let timestamp = b.timestamp * 1000000000;
+
+    let parent_num = if b.previous_block.is_empty() {
+        0
+    } else {
+        block_num - 1
+    };
+
+    let lib = block_num.saturating_sub(confirms);
+
+    let encoded = pb::Block::new(b, network);
+    let block_payload = if quiet {
+        "<trimmed>".to_string()
+    } else {
+        general_purpose::STANDARD.encode(encoded.encode_to_vec())
+    };
+
+    Ok(format!(
+        "FIRE BLOCK {} {} {} {} {} {} {}",
+        block_num, block_hash, parent_num, parent_hash, lib, timestamp, block_payload
+    ))
+}
+
+/// block of a `FIRE BLOCK` line, the number and hash of the line must match
+/// its payload
+///
+/// ```rust
+/// use thegarii::{firehose, network::Network, types::{Block, FirehoseBlock}};
+///
+/// let block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+/// let line = firehose::block_line(FirehoseBlock::from(block), &Network::mainnet(), 20, false).unwrap();
+/// assert_eq!(firehose::parse_block_line(&line).unwrap().height, 100);
+///
+/// assert!(firehose::parse_block_line(&line.replacen("100", "101", 1)).is_err());
+/// assert!(firehose::parse_block_line(firehose::INIT).is_err());
+/// ```
+pub fn parse_block_line(line: &str) -> Result<pb::Block> {
+    let fields = line.split(' ').collect::<Vec<_>>();
+    if fields.len() != 9 || fields[..2] != ["FIRE", "BLOCK"] {
+        return Err(Error::InvalidPayload("not a FIRE BLOCK line".into()));
+    }
+
+    let block = parse_payload(fields[8])?;
+    if fields[2] != block.height.to_string() {
+        return Err(Error::InvalidPayload(format!(
+            "line of block {} carries block {}",
+            fields[2], block.height
+        )));
+    }
+    if fields[3] != hex::encode(&block.indep_hash) {
+        return Err(Error::InvalidPayload(format!(
+            "line of hash {} carries hash {}",
+            fields[3],
+            hex::encode(&block.indep_hash)
+        )));
+    }
+
+    Ok(block)
+}
+
+/// block of a base64 encoded protobuf payload
+pub fn parse_payload(payload: &str) -> Result<pb::Block> {
+    if payload == "<trimmed>" {
+        return Err(Error::InvalidPayload("payload has been trimmed".into()));
+    }
+
+    let bytes = general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| Error::InvalidPayload(format!("invalid base64: {}", e)))?;
+    pb::Block::decode(bytes.as_slice())
+        .map_err(|e| Error::InvalidPayload(format!("invalid protobuf: {}", e)))
+}
+
+/// human readable JSON of a firehose block, bytes are base64url encoded and
+/// big integers are decimal strings
+pub fn block_json(block: &pb::Block) -> Value {
+    Render::default().block_json(block)
+}
+
+/// human readable JSON of a firehose transaction
+pub fn tx_json(tx: &pb::Transaction) -> Value {
+    Render::default().tx_json(tx)
+}
+
+/// decimal string of a big integer, `null` if unset
+fn big_int(n: &Option<BigInt>) -> Value {
+    match n {
+        Some(n) if !n.bytes.is_empty() => {
+            Value::String(U256::from_big_endian(&n.bytes).to_string())
+        }
+        _ => Value::Null,
+    }
+}
+
+/// fraction as `[numerator, denominator]` decimal strings
+fn rate(rate: &pb::Rate) -> Value {
+    json!([big_int(&rate.numerator), big_int(&rate.denominator)])
+}
+
+/// how the JSON of firehose blocks renders bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Render {
+    /// bytes as hex instead of base64url
+    pub hex: bool,
+    /// names and values of tags as utf-8 text where valid
+    pub utf8_tags: bool,
+    /// `tx_root` and `hash_list_merkle`, carried as base64url text, as the
+    /// bytes they encode
+    pub text_roots: bool,
+}
+
+impl Render {
+    /// encoded bytes
+    fn bytes(&self, bytes: &[u8]) -> Value {
+        Value::String(if self.hex {
+            hex::encode(bytes)
+        } else {
+            base64_url::encode(bytes)
+        })
+    }
+
+    /// root carried as base64url text
+    fn root(&self, bytes: &[u8]) -> Value {
+        let decoded = std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| base64_url::decode(text).ok());
+        match decoded {
+            Some(decoded) if self.text_roots => self.bytes(&decoded),
+            _ => self.bytes(bytes),
+        }
+    }
+
+    /// name or value of a tag
+    fn tag_field(&self, bytes: &[u8]) -> Value {
+        match std::str::from_utf8(bytes) {
+            Ok(text) if self.utf8_tags => Value::String(text.into()),
+            _ => self.bytes(bytes),
+        }
+    }
+
+    /// tags with encoded names and values
+    fn tags(&self, tags: &[pb::Tag]) -> Value {
+        tags.iter()
+            .map(|tag| json!({ "name": self.tag_field(&tag.name), "value": self.tag_field(&tag.value) }))
+            .collect()
+    }
+
+    /// human readable JSON of a firehose block, big integers are decimal
+    /// strings
+    ///
+    /// ```rust
+    /// use thegarii::{firehose::Render, network::Network, pb, types::{Block, FirehoseBlock}};
+    ///
+    /// let mainnet = Network::mainnet();
+    /// let block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+    /// let block = pb::Block::new(FirehoseBlock::from(block), &mainnet);
+    ///
+    /// let render = Render { hex: true, utf8_tags: true, text_roots: false };
+    /// let json = render.block_json(&block);
+    /// assert_eq!(json["indep_hash"], hex::encode(&block.indep_hash));
+    /// assert_eq!(json["diff"], "31");
+    ///
+    /// // roots are carried as base64url text
+    /// let block: Block = serde_json::from_str(include_str!("../res/block_height_422250.json")).unwrap();
+    /// let block = pb::Block::new(FirehoseBlock::from(block), &mainnet);
+    /// let tx_root = "lsoo-p3Tj7oblZ-54WVPHoVguqgw5rA9Jf3lLH6H8zY";
+    /// assert_eq!(block.tx_root, tx_root.as_bytes());
+    /// assert_eq!(Render::default().block_json(&block)["tx_root"], base64_url::encode(tx_root));
+    /// let render = Render { text_roots: true, ..Default::default() };
+    /// assert_eq!(render.block_json(&block)["tx_root"], tx_root);
+    /// ```
+    pub fn block_json(&self, block: &pb::Block) -> Value {
+        let mut json = json!({
+            "ver": block.ver,
+            "indep_hash": self.bytes(&block.indep_hash),
+            "nonce": self.bytes(&block.nonce),
+            "previous_block": self.bytes(&block.previous_block),
+            "timestamp": block.timestamp,
+            "last_retarget": block.last_retarget,
+            "diff": big_int(&block.diff),
+            "height": block.height,
+            "hash": self.bytes(&block.hash),
+            "tx_root": self.root(&block.tx_root),
+            "txs": block.txs.iter().map(|tx| self.tx_json(tx)).collect::<Vec<_>>(),
+            "wallet_list": self.bytes(&block.wallet_list),
+            "reward_addr": self.bytes(&block.reward_addr),
+            "tags": self.tags(&block.tags),
+            "reward_pool": big_int(&block.reward_pool),
+            "weave_size": big_int(&block.weave_size),
+            "block_size": big_int(&block.block_size),
+            "cumulative_diff": big_int(&block.cumulative_diff),
+            "hash_list_merkle": self.root(&block.hash_list_merkle),
+            "poa": block.poa.as_ref().map(|poa| self.poa(poa)),
+            "filtered": block.filtered,
+        });
+
+        if let (Value::Object(json), Value::Object(fields)) = (&mut json, self.fork_fields(block)) {
+            json.extend(fields);
+        }
+        json
+    }
+
+    /// fields of the blocks since the 2.5 fork
+    fn fork_fields(&self, block: &pb::Block) -> Value {
+        json!({
+            "usd_to_ar_rate": block.usd_to_ar_rate.as_ref().map(rate),
+            "scheduled_usd_to_ar_rate": block.scheduled_usd_to_ar_rate.as_ref().map(rate),
+            "packing_2_5_threshold": big_int(&block.packing_2_5_threshold),
+            "strict_data_split_threshold": big_int(&block.strict_data_split_threshold),
+            "hash_preimage": self.bytes(&block.hash_preimage),
+            "recall_byte": big_int(&block.recall_byte),
+            "reward": big_int(&block.reward),
+            "previous_solution_hash": self.bytes(&block.previous_solution_hash),
+            "partition_number": block.partition_number,
+            "nonce_limiter_info": block
+                .nonce_limiter_info
+                .as_ref()
+                .map(|info| self.nonce_limiter_info(info)),
+            "poa2": block.poa2.as_ref().map(|poa| self.poa(poa)),
+            "signature": self.bytes(&block.signature),
+            "reward_key": self.bytes(&block.reward_key),
+            "price_per_gib_minute": big_int(&block.price_per_gib_minute),
+            "scheduled_price_per_gib_minute": big_int(&block.scheduled_price_per_gib_minute),
+            "reward_history_hash": self.bytes(&block.reward_history_hash),
+            "debt_supply": big_int(&block.debt_supply),
+            "kryder_plus_rate_multiplier": big_int(&block.kryder_plus_rate_multiplier),
+            "kryder_plus_rate_multiplier_latch": big_int(&block.kryder_plus_rate_multiplier_latch),
+            "denomination": big_int(&block.denomination),
+            "redenomination_height": block.redenomination_height,
+            "double_signing_proof": block
+                .double_signing_proof
+                .as_ref()
+                .map(|proof| self.double_signing_proof(proof)),
+            "previous_cumulative_diff": big_int(&block.previous_cumulative_diff),
+            "merkle_rebase_support_threshold": big_int(&block.merkle_rebase_support_threshold),
+            "chunk_hash": self.bytes(&block.chunk_hash),
+            "chunk2_hash": self.bytes(&block.chunk2_hash),
+            "block_time_history_hash": self.bytes(&block.block_time_history_hash),
+            "recall_byte2": big_int(&block.recall_byte2),
+        })
+    }
+
+    /// proof of access with encoded paths and chunk
+    fn poa(&self, poa: &pb::ProofOfAccess) -> Value {
+        json!({
+            "option": poa.option,
+            "tx_path": self.bytes(&poa.tx_path),
+            "data_path": self.bytes(&poa.data_path),
+            "chunk": self.bytes(&poa.chunk),
+        })
+    }
+
+    /// verifiable delay function state with encoded outputs
+    fn nonce_limiter_info(&self, info: &pb::NonceLimiterInfo) -> Value {
+        json!({
+            "output": self.bytes(&info.output),
+            "global_step_number": info.global_step_number,
+            "seed": self.bytes(&info.seed),
+            "next_seed": self.bytes(&info.next_seed),
+            "zone_upper_bound": big_int(&info.zone_upper_bound),
+            "next_zone_upper_bound": big_int(&info.next_zone_upper_bound),
+            "prev_output": self.bytes(&info.prev_output),
+            "last_step_checkpoints": info
+                .last_step_checkpoints
+                .iter()
+                .map(|c| self.bytes(c))
+                .collect::<Vec<_>>(),
+            "checkpoints": info.checkpoints.iter().map(|c| self.bytes(c)).collect::<Vec<_>>(),
+            "vdf_difficulty": info.vdf_difficulty,
+            "next_vdf_difficulty": info.next_vdf_difficulty,
+        })
+    }
+
+    /// double signing proof with encoded keys and signatures
+    fn double_signing_proof(&self, proof: &pb::DoubleSigningProof) -> Value {
+        json!({
+            "pub_key": self.bytes(&proof.pub_key),
+            "sig1": self.bytes(&proof.sig1),
+            "cdiff1": big_int(&proof.cdiff1),
+            "prev_cdiff1": big_int(&proof.prev_cdiff1),
+            "preimage1": self.bytes(&proof.preimage1),
+            "sig2": self.bytes(&proof.sig2),
+            "cdiff2": big_int(&proof.cdiff2),
+            "prev_cdiff2": big_int(&proof.prev_cdiff2),
+            "preimage2": self.bytes(&proof.preimage2),
+        })
+    }
+
+    /// human readable JSON of a firehose transaction
+    pub fn tx_json(&self, tx: &pb::Transaction) -> Value {
+        json!({
+            "format": tx.format,
+            "id": self.bytes(&tx.id),
+            "last_tx": self.bytes(&tx.last_tx),
+            "owner": self.bytes(&tx.owner),
+            "tags": self.tags(&tx.tags),
+            "target": self.bytes(&tx.target),
+            "quantity": big_int(&tx.quantity),
+            "data": self.bytes(&tx.data),
+            "data_size": big_int(&tx.data_size),
+            "data_root": self.bytes(&tx.data_root),
+            "signature": self.bytes(&tx.signature),
+            "reward": big_int(&tx.reward),
+            "data_status": tx.data_status().as_str_name(),
+            "signature_status": tx.signature_status().as_str_name(),
+        })
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! liveness and readiness of the polling service
+use serde_json::{json, Value};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+struct State {
+    head: Option<u64>,
+    lib: Option<u64>,
+    ptr: Option<u64>,
+    last_head: Option<Instant>,
+    last_advance: Option<Instant>,
+    last_progress: Option<Instant>,
+}
+
+/// health of the polling service shared with the http endpoints
+///
+/// ```rust
+/// use std::time::Duration;
+/// use thegarii::health::Health;
+///
+/// let health = Health::new(Duration::from_secs(60), Duration::from_secs(60));
+/// assert!(health.live().0);
+/// assert!(!health.ready().0);
+///
+/// health.on_head(422270, 422250);
+/// assert!(!health.ready().0);
+///
+/// health.on_advance(100);
+/// let (ready, details) = health.ready();
+/// assert!(ready);
+/// assert_eq!(details["ptr"], 100);
+/// ```
+#[derive(Debug)]
+pub struct Health {
+    started: Instant,
+    ready_window: Duration,
+    live_window: Duration,
+    state: Mutex<State>,
+}
+
+impl Health {
+    /// new health state, ready if the pointer advanced within `ready_window`
+    /// and live if the poll loop progressed within `live_window`
+    pub fn new(ready_window: Duration, live_window: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            ready_window,
+            live_window,
+            state: Default::default(),
+        }
+    }
+
+    /// record a successful head fetch
+    pub fn on_head(&self, head: u64, lib: u64) {
+        let mut state = self.state.lock().expect("health state poisoned");
+        let now = Instant::now();
+        state.head = Some(head);
+        state.lib = Some(lib);
+        state.last_head = Some(now);
+        state.last_progress = Some(now);
+    }
+
+    /// record an advanced pointer
+    pub fn on_advance(&self, ptr: u64) {
+        let mut state = self.state.lock().expect("health state poisoned");
+        let now = Instant::now();
+        state.ptr = Some(ptr);
+        state.last_advance = Some(now);
+        state.last_progress = Some(now);
+    }
+
+    /// record an iteration of the poll loop
+    pub fn on_progress(&self) {
+        self.state
+            .lock()
+            .expect("health state poisoned")
+            .last_progress = Some(Instant::now());
+    }
+
+    /// if the poll loop progressed within the live window, with details
+    pub fn live(&self) -> (bool, Value) {
+        let state = self.state.lock().expect("health state poisoned");
+        let since_progress = state.last_progress.unwrap_or(self.started).elapsed();
+        let live = since_progress <= self.live_window;
+
+        (live, self.details(&state, json!({ "live": live })))
+    }
+
+    /// if the first head fetch succeeded and the pointer advanced within
+    /// the ready window, with details
+    pub fn ready(&self) -> (bool, Value) {
+        let state = self.state.lock().expect("health state poisoned");
+        let ready = state.last_head.is_some()
+            && state
+                .last_advance
+                .map(|at| at.elapsed() <= self.ready_window)
+                .unwrap_or(false);
+
+        (ready, self.details(&state, json!({ "ready": ready })))
+    }
+
+    /// details of the current state
+    fn details(&self, state: &State, mut status: Value) -> Value {
+        let secs = |at: Option<Instant>| at.map(|at| at.elapsed().as_secs_f64());
+        let details = json!({
+            "head": state.head,
+            "lib": state.lib,
+            "ptr": state.ptr,
+            "uptime_secs": self.started.elapsed().as_secs_f64(),
+            "secs_since_head": secs(state.last_head),
+            "secs_since_advance": secs(state.last_advance),
+            "secs_since_progress": secs(state.last_progress),
+            "ready_window_secs": self.ready_window.as_secs(),
+            "live_window_secs": self.live_window.as_secs(),
+        });
+
+        if let (Value::Object(status), Value::Object(details)) = (&mut status, details) {
+            status.extend(details);
+        }
+        status
+    }
+}
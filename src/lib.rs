@@ -4,14 +4,26 @@
 //! the garii project
 //!
 //! this library fetches blocks from Arweave and generates firehose blocks for the Graph
+pub mod archive;
 pub mod client;
 pub mod cmd;
-mod encoding;
+pub mod concurrency;
+pub mod encoding;
 pub mod env;
+pub mod filter;
+pub mod firehose;
+pub mod health;
+pub mod metrics;
+pub mod network;
 pub mod pb;
 mod polling;
+pub mod replay;
 pub mod result;
+mod server;
+#[cfg(feature = "full")]
+pub mod storage;
 pub mod types;
+pub mod verify;
 
 pub use self::{
     client::Client,
@@ -0,0 +1,193 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! prometheus metrics
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::sync::LazyLock;
+
+/// height of the chain head
+pub static HEAD: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("thegarii_head", "height of the chain head").expect("valid metric")
+});
+
+/// height of the last irreversible block
+pub static LIB: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("thegarii_lib", "height of the last irreversible block")
+        .expect("valid metric")
+});
+
+/// next block to be emitted
+pub static PTR: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("thegarii_ptr", "next block to be emitted").expect("valid metric")
+});
+
+/// blocks between the chain head and the pointer
+pub static LAG_BLOCKS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "thegarii_lag_blocks",
+        "blocks between the chain head and the pointer"
+    )
+    .expect("valid metric")
+});
+
+/// seconds between now and the timestamp of the last emitted block
+pub static LAG_SECONDS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "thegarii_lag_seconds",
+        "seconds between now and the timestamp of the last emitted block"
+    )
+    .expect("valid metric")
+});
+
+/// blocks emitted to stdout
+pub static BLOCKS_EMITTED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("thegarii_blocks_emitted_total", "blocks emitted to stdout")
+        .expect("valid metric")
+});
+
+/// transactions emitted to stdout
+pub static TXS_EMITTED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "thegarii_txs_emitted_total",
+        "transactions emitted to stdout"
+    )
+    .expect("valid metric")
+});
+
+/// transactions failing signature or id verification
+pub static TXS_INVALID: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "thegarii_txs_invalid_total",
+        "transactions failing signature or id verification"
+    )
+    .expect("valid metric")
+});
+
+/// bytes of response bodies fetched from endpoints
+pub static BYTES_FETCHED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "thegarii_bytes_fetched_total",
+        "bytes of response bodies fetched from endpoints"
+    )
+    .expect("valid metric")
+});
+
+/// http requests sent per endpoint
+pub static REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "thegarii_requests_total",
+        "http requests sent per endpoint",
+        &["endpoint"]
+    )
+    .expect("valid metric")
+});
+
+/// failed http requests per endpoint
+pub static REQUEST_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "thegarii_request_errors_total",
+        "failed http requests per endpoint",
+        &["endpoint"]
+    )
+    .expect("valid metric")
+});
+
+/// latency of successful http requests per endpoint
+pub static REQUEST_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "thegarii_request_latency_seconds",
+        "latency of successful http requests per endpoint",
+        &["endpoint"],
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]
+    )
+    .expect("valid metric")
+});
+
+/// retried http requests per endpoint
+pub static RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "thegarii_retries_total",
+        "retried http requests per endpoint",
+        &["endpoint"]
+    )
+    .expect("valid metric")
+});
+
+/// restarts of the poller performed by `--forever`
+pub static RESTARTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "thegarii_restarts_total",
+        "restarts of the poller performed by --forever"
+    )
+    .expect("valid metric")
+});
+
+/// current limit of blocks fetched at the same time
+pub static CONCURRENCY: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "thegarii_concurrency",
+        "current limit of blocks fetched at the same time"
+    )
+    .expect("valid metric")
+});
+
+/// bytes of fetched blocks waiting for being emitted
+pub static BUFFERED_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "thegarii_buffered_bytes",
+        "bytes of fetched blocks waiting for being emitted"
+    )
+    .expect("valid metric")
+});
+
+/// register all metrics, so they are exposed before being recorded
+fn register() {
+    for gauge in [
+        &HEAD,
+        &LIB,
+        &PTR,
+        &LAG_BLOCKS,
+        &LAG_SECONDS,
+        &CONCURRENCY,
+        &BUFFERED_BYTES,
+    ] {
+        LazyLock::force(gauge);
+    }
+    for counter in [
+        &BLOCKS_EMITTED,
+        &TXS_EMITTED,
+        &TXS_INVALID,
+        &BYTES_FETCHED,
+        &RESTARTS,
+    ] {
+        LazyLock::force(counter);
+    }
+    for counter in [&REQUESTS, &REQUEST_ERRORS, &RETRIES] {
+        LazyLock::force(counter);
+    }
+    LazyLock::force(&REQUEST_LATENCY);
+}
+
+/// encode all registered metrics with the prometheus text format
+///
+/// ```rust
+/// use thegarii::metrics;
+///
+/// metrics::HEAD.set(422250);
+/// assert!(metrics::gather().contains("thegarii_head 422250"));
+/// assert!(metrics::gather().contains("thegarii_restarts_total 0"));
+/// assert!(metrics::gather().contains("thegarii_txs_invalid_total 0"));
+/// ```
+pub fn gather() -> String {
+    register();
+
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("encoding metrics into a vec never fails");
+
+    String::from_utf8(buffer).expect("prometheus text format is utf-8")
+}
@@ -0,0 +1,177 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! arweave networks and the activation heights of their forks
+//!
+//! clients, archives and encoders are given the network of their blocks,
+//! i.e. mainnet, a testnet or a local arlocal chain
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, fs, path::Path, str::FromStr};
+
+/// protocol upgrades of arweave
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Fork {
+    #[serde(rename = "1.6")]
+    V1_6,
+    #[serde(rename = "1.7")]
+    V1_7,
+    /// `cumulative_diff` and `hash_list_merkle`, string difficulties
+    #[serde(rename = "1.8")]
+    V1_8,
+    #[serde(rename = "1.9")]
+    V1_9,
+    /// `tx_root` and format 2 transactions
+    #[serde(rename = "2.0")]
+    V2_0,
+    #[serde(rename = "2.2")]
+    V2_2,
+    #[serde(rename = "2.3")]
+    V2_3,
+    /// `poa` is set
+    #[serde(rename = "2.4")]
+    V2_4,
+    /// usd to ar rates and data padded to whole chunks
+    #[serde(rename = "2.5")]
+    V2_5,
+    /// nonce limiter and signed blocks
+    #[serde(rename = "2.6")]
+    V2_6,
+    #[serde(rename = "2.6.8")]
+    V2_6_8,
+    /// merkle rebase and chunk hashes
+    #[serde(rename = "2.7")]
+    V2_7,
+    #[serde(rename = "2.7.1")]
+    V2_7_1,
+    #[serde(rename = "2.7.2")]
+    V2_7_2,
+    #[serde(rename = "2.8")]
+    V2_8,
+    #[serde(rename = "2.9")]
+    V2_9,
+}
+
+impl Fork {
+    /// all forks in activation order
+    pub const ALL: [Fork; 16] = [
+        Fork::V1_6,
+        Fork::V1_7,
+        Fork::V1_8,
+        Fork::V1_9,
+        Fork::V2_0,
+        Fork::V2_2,
+        Fork::V2_3,
+        Fork::V2_4,
+        Fork::V2_5,
+        Fork::V2_6,
+        Fork::V2_6_8,
+        Fork::V2_7,
+        Fork::V2_7_1,
+        Fork::V2_7_2,
+        Fork::V2_8,
+        Fork::V2_9,
+    ];
+}
+
+impl fmt::Display for Fork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = format!("{:?}", self);
+        write!(f, "{}", version[1..].replace('_', "."))
+    }
+}
+
+/// an arweave network and its fork schedule
+///
+/// schedules are loaded from JSON files, forks missing from them never
+/// activate
+///
+/// ```rust
+/// use thegarii::network::{Fork, Network};
+///
+/// let testnet: Network = serde_json::from_str(r#"{
+///     "name": "testnet",
+///     "forks": { "1.8": 0, "2.0": 10, "2.5": 20 }
+/// }"#).unwrap();
+/// assert!(!testnet.activated(Fork::V2_0, 9));
+/// assert!(testnet.activated(Fork::V2_0, 10));
+/// assert_eq!(testnet.fork_at(15), Some(Fork::V2_0));
+/// assert!(!testnet.activated(Fork::V2_6, u64::MAX));
+///
+/// assert_eq!(Network::mainnet().fork_at(422250), Some(Fork::V2_0));
+/// assert_eq!(Network::mainnet().fork_at(269509), Some(Fork::V1_7));
+/// assert_eq!(Network::mainnet().fork_at(1189560).unwrap().to_string(), "2.6.8");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Network {
+    /// name of the network, i.e. `mainnet`
+    pub name: String,
+    /// activation heights of the forks
+    pub forks: BTreeMap<Fork, u64>,
+}
+
+impl Network {
+    /// the arweave mainnet
+    pub fn mainnet() -> Self {
+        let heights = [
+            95000, 235200, 269510, 315700, 422250, 552180, 591140, 633720, 812970, 1132210,
+            1189560, 1275480, 1316410, 1391330, 1547120, 1602350,
+        ];
+
+        Self {
+            name: "mainnet".to_string(),
+            forks: Fork::ALL.into_iter().zip(heights).collect(),
+        }
+    }
+
+    /// local chains like arlocal, every fork is active from the genesis
+    pub fn local() -> Self {
+        Self {
+            name: "local".to_string(),
+            forks: Fork::ALL.into_iter().map(|fork| (fork, 0)).collect(),
+        }
+    }
+
+    /// load a schedule from the JSON file at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path)?;
+        crate::encoding::from_slice(&json, &path.to_string_lossy())
+    }
+
+    /// activation height of `fork`, `None` if it never activates
+    pub fn height(&self, fork: Fork) -> Option<u64> {
+        self.forks.get(&fork).copied()
+    }
+
+    /// if `fork` is active at `height`
+    pub fn activated(&self, fork: Fork, height: u64) -> bool {
+        self.height(fork).is_some_and(|h| height >= h)
+    }
+
+    /// latest fork active at `height`, `None` before the first one
+    pub fn fork_at(&self, height: u64) -> Option<Fork> {
+        Fork::ALL
+            .into_iter()
+            .rev()
+            .find(|fork| self.activated(*fork, height))
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl FromStr for Network {
+    type Err = Error;
+
+    /// `mainnet`, `local` or the path of a JSON schedule
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mainnet" => Ok(Self::mainnet()),
+            "local" => Ok(Self::local()),
+            path => Self::load(Path::new(path)),
+        }
+    }
+}
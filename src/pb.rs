@@ -2,42 +2,16 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 #![allow(missing_docs)]
 use crate::{
-    result::{Error, Result},
-    types::{self, FirehoseBlock, Poa, U256},
+    network::{Fork, Network},
+    types::{self, BigUint, FirehoseBlock, Poa, U256},
+    Error, Result,
 };
-use core::convert::{TryFrom, TryInto};
 
 pub mod sf {
     pub mod arweave {
         pub mod r#type {
             pub mod v1 {
-                use crate::{types::U256, Error, Result};
-
                 include!(concat!(env!("OUT_DIR"), "/sf.arweave.r#type.v1.rs"));
-
-                impl TryFrom<String> for BigInt {
-                    type Error = Error;
-
-                    fn try_from(s: String) -> Result<Self> {
-                        Ok(Self {
-                            bytes: U256::from_dec_str(&s)?.to_be(),
-                        })
-                    }
-                }
-
-                impl TryFrom<Option<String>> for BigInt {
-                    type Error = Error;
-
-                    fn try_from(os: Option<String>) -> Result<Self> {
-                        if let Some(s) = os {
-                            s.try_into()
-                        } else {
-                            Ok(Self {
-                                bytes: Default::default(),
-                            })
-                        }
-                    }
-                }
             }
         }
     }
@@ -45,100 +19,474 @@ pub mod sf {
 
 pub use self::sf::arweave::r#type::v1::*;
 
-/// decode string to bytes with base64url
-fn bd(s: &str) -> Result<Vec<u8>> {
-    // parse empty reward addr to vec![]
-    if s == "unclaimed" {
-        return Ok(vec![]);
+impl From<BigUint> for BigInt {
+    fn from(n: BigUint) -> Self {
+        Self { bytes: n.0.to_be() }
     }
-
-    base64_url::decode(s).map_err(Into::into)
 }
 
-impl TryFrom<FirehoseBlock> for Block {
+impl TryFrom<BigInt> for BigUint {
     type Error = Error;
 
-    fn try_from(block: FirehoseBlock) -> Result<Self> {
-        Ok(Self {
+    fn try_from(n: BigInt) -> Result<Self> {
+        let start = n
+            .bytes
+            .iter()
+            .position(|b| *b != 0)
+            .unwrap_or(n.bytes.len());
+        if n.bytes.len() - start > 32 {
+            return Err(Error::InvalidPayload(format!(
+                "big integer of {} bytes",
+                n.bytes.len() - start
+            )));
+        }
+
+        Ok(Self(U256::from_big_endian(&n.bytes[start..])))
+    }
+}
+
+/// bytes of an optional field, empty if unset
+fn bytes(b: Option<types::Base64Url>) -> Vec<u8> {
+    b.map(Into::into).unwrap_or_default()
+}
+
+/// base64url text of a root, carried as its utf-8 bytes as FIRE payloads
+/// always have
+fn text(root: Option<types::Base64Url>) -> Vec<u8> {
+    root.map(|root| root.to_string().into_bytes())
+        .unwrap_or_default()
+}
+
+/// root carried as base64url text
+fn root(bytes: Vec<u8>, field: &str) -> Result<types::Base64Url> {
+    String::from_utf8(bytes)
+        .map_err(|_| Error::InvalidPayload(format!("{} is not utf-8", field)))?
+        .parse()
+        .map_err(|_| Error::InvalidPayload(format!("{} is not base64url", field)))
+}
+
+/// big integer of a message field, zero if unset
+fn big_uint(n: Option<BigInt>) -> Result<BigUint> {
+    n.map(TryInto::try_into)
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+/// field of `fork`, set if the fork is active at `height` on `network` or if
+/// not empty
+fn since<T: Default + PartialEq>(
+    network: &Network,
+    fork: Fork,
+    height: u64,
+    value: T,
+) -> Option<T> {
+    (value != T::default() || network.activated(fork, height)).then_some(value)
+}
+
+impl Block {
+    /// message of `block`, whose fields follow the forks of `network`
+    pub fn new(block: FirehoseBlock, network: &Network) -> Self {
+        // blocks only commit to their txs since 2.0
+        let tx_root = if network.activated(Fork::V2_0, block.height) {
+            text(block.tx_root)
+        } else {
+            vec![]
+        };
+
+        Self {
             ver: 1,
-            indep_hash: bd(&block.indep_hash)?,
-            nonce: bd(&block.nonce)?,
-            previous_block: bd(&block.previous_block)?,
+            indep_hash: block.indep_hash.into(),
+            nonce: block.nonce.into(),
+            previous_block: block.previous_block.into(),
             timestamp: block.timestamp,
             last_retarget: block.last_retarget,
-            diff: Some(block.diff.try_into()?),
+            diff: Some(block.diff.into()),
             height: block.height,
-            hash: bd(&block.hash)?,
-            tx_root: block.tx_root.unwrap_or_default().into(),
+            hash: block.hash.into(),
+            tx_root,
+            txs: block.txs.into_iter().map(Into::into).collect(),
+            wallet_list: block.wallet_list.into(),
+            reward_addr: block.reward_addr.into(),
+            tags: block.tags.into_iter().map(Into::into).collect(),
+            reward_pool: Some(block.reward_pool.into()),
+            weave_size: Some(block.weave_size.into()),
+            block_size: Some(block.block_size.into()),
+            cumulative_diff: Some(block.cumulative_diff.map(Into::into).unwrap_or_default()),
+            hash_list_merkle: text(block.hash_list_merkle),
+            poa: block.poa.map(Into::into),
+            filtered: block.filtered,
+            usd_to_ar_rate: block.usd_to_ar_rate.map(Into::into),
+            scheduled_usd_to_ar_rate: block.scheduled_usd_to_ar_rate.map(Into::into),
+            packing_2_5_threshold: block.packing_2_5_threshold.map(Into::into),
+            strict_data_split_threshold: block.strict_data_split_threshold.map(Into::into),
+            hash_preimage: bytes(block.hash_preimage),
+            recall_byte: block.recall_byte.map(Into::into),
+            reward: block.reward.map(Into::into),
+            previous_solution_hash: bytes(block.previous_solution_hash),
+            partition_number: block.partition_number.unwrap_or_default(),
+            nonce_limiter_info: block.nonce_limiter_info.map(Into::into),
+            poa2: block.poa2.map(Into::into),
+            signature: bytes(block.signature),
+            reward_key: bytes(block.reward_key),
+            price_per_gib_minute: block.price_per_gib_minute.map(Into::into),
+            scheduled_price_per_gib_minute: block.scheduled_price_per_gib_minute.map(Into::into),
+            reward_history_hash: bytes(block.reward_history_hash),
+            debt_supply: block.debt_supply.map(Into::into),
+            kryder_plus_rate_multiplier: block.kryder_plus_rate_multiplier.map(Into::into),
+            kryder_plus_rate_multiplier_latch: block
+                .kryder_plus_rate_multiplier_latch
+                .map(Into::into),
+            denomination: block.denomination.map(Into::into),
+            redenomination_height: block.redenomination_height.unwrap_or_default(),
+            double_signing_proof: block.double_signing_proof.map(Into::into),
+            previous_cumulative_diff: block.previous_cumulative_diff.map(Into::into),
+            merkle_rebase_support_threshold: block.merkle_rebase_support_threshold.map(Into::into),
+            chunk_hash: bytes(block.chunk_hash),
+            chunk2_hash: bytes(block.chunk2_hash),
+            block_time_history_hash: bytes(block.block_time_history_hash),
+            recall_byte2: block.recall_byte2.map(Into::into),
+        }
+    }
+}
+
+impl From<Poa> for ProofOfAccess {
+    fn from(poa: Poa) -> Self {
+        Self {
+            option: poa.option,
+            tx_path: poa.tx_path.into(),
+            data_path: poa.data_path.into(),
+            chunk: poa.chunk.into(),
+        }
+    }
+}
+
+impl From<types::Rate> for Rate {
+    fn from(rate: types::Rate) -> Self {
+        Self {
+            numerator: Some(rate.0.into()),
+            denominator: Some(rate.1.into()),
+        }
+    }
+}
+
+impl From<types::NonceLimiterInfo> for NonceLimiterInfo {
+    fn from(info: types::NonceLimiterInfo) -> Self {
+        Self {
+            output: info.output.into(),
+            global_step_number: info.global_step_number,
+            seed: info.seed.into(),
+            next_seed: info.next_seed.into(),
+            zone_upper_bound: Some(info.zone_upper_bound.into()),
+            next_zone_upper_bound: Some(info.next_zone_upper_bound.into()),
+            prev_output: info.prev_output.into(),
+            last_step_checkpoints: info
+                .last_step_checkpoints
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            checkpoints: info.checkpoints.into_iter().map(Into::into).collect(),
+            vdf_difficulty: info.vdf_difficulty.unwrap_or_default(),
+            next_vdf_difficulty: info.next_vdf_difficulty.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<types::DoubleSigningProof> for DoubleSigningProof {
+    fn from(proof: types::DoubleSigningProof) -> Self {
+        Self {
+            pub_key: proof.pub_key.into(),
+            sig1: proof.sig1.into(),
+            cdiff1: Some(proof.cdiff1.into()),
+            prev_cdiff1: Some(proof.prev_cdiff1.into()),
+            preimage1: proof.preimage1.into(),
+            sig2: proof.sig2.into(),
+            cdiff2: Some(proof.cdiff2.into()),
+            prev_cdiff2: Some(proof.prev_cdiff2.into()),
+            preimage2: proof.preimage2.into(),
+        }
+    }
+}
+
+impl From<types::Transaction> for Transaction {
+    fn from(tx: types::Transaction) -> Self {
+        Self {
+            format: tx.format.unwrap_or_default(),
+            id: tx.id.into(),
+            last_tx: tx.last_tx.into(),
+            owner: tx.owner.into(),
+            tags: tx.tags.into_iter().map(Into::into).collect(),
+            target: tx.target.into(),
+            quantity: Some(tx.quantity.into()),
+            data: tx.data.into(),
+            data_size: Some(tx.data_size.into()),
+            data_root: tx.data_root.into(),
+            signature: tx.signature.into(),
+            reward: Some(tx.reward.into()),
+            data_status: match tx.data_status {
+                types::DataStatus::Inline => DataStatus::Inline,
+                types::DataStatus::Fetched => DataStatus::Fetched,
+                types::DataStatus::Skipped => DataStatus::Skipped,
+            } as i32,
+            signature_status: match tx.signature_status {
+                types::SignatureStatus::Unverified => SignatureStatus::Unverified,
+                types::SignatureStatus::Valid => SignatureStatus::Valid,
+                types::SignatureStatus::Invalid => SignatureStatus::Invalid,
+            } as i32,
+        }
+    }
+}
+
+impl From<types::Tag> for Tag {
+    fn from(tag: types::Tag) -> Self {
+        Self {
+            name: tag.name.into(),
+            value: tag.value.into(),
+        }
+    }
+}
+
+impl Block {
+    /// firehose block of this message, fields of forks not active on
+    /// `network` are `None` unless set
+    pub fn into_firehose(self, network: &Network) -> Result<FirehoseBlock> {
+        let block = self;
+        let height = block.height;
+        let big_uint_since = |fork, n: Option<BigInt>| -> Result<Option<BigUint>> {
+            n.map(BigUint::try_from)
+                .transpose()
+                .map(|n| n.and_then(|n| since(network, fork, height, n)))
+        };
+
+        Ok(FirehoseBlock {
+            ver: block.ver,
+            indep_hash: block.indep_hash.into(),
+            nonce: block.nonce.into(),
+            previous_block: block.previous_block.into(),
+            timestamp: block.timestamp,
+            last_retarget: block.last_retarget,
+            diff: big_uint(block.diff)?,
+            height,
+            hash: block.hash.into(),
+            tx_root: Some(root(block.tx_root, "tx_root")?),
             txs: block
                 .txs
                 .into_iter()
                 .map(TryInto::try_into)
-                .collect::<Result<Vec<_>>>()?,
-            wallet_list: bd(&block.wallet_list)?,
-            reward_addr: bd(&block.reward_addr)?,
-            tags: block
-                .tags
-                .into_iter()
+                .collect::<Result<_>>()?,
+            wallet_list: block.wallet_list.into(),
+            reward_addr: block.reward_addr.into(),
+            tags: block.tags.into_iter().map(Into::into).collect(),
+            reward_pool: big_uint(block.reward_pool)?,
+            weave_size: big_uint(block.weave_size)?,
+            block_size: big_uint(block.block_size)?,
+            cumulative_diff: since(
+                network,
+                Fork::V1_8,
+                height,
+                big_uint(block.cumulative_diff)?,
+            ),
+            hash_list_merkle: since(
+                network,
+                Fork::V1_8,
+                height,
+                root(block.hash_list_merkle, "hash_list_merkle")?,
+            ),
+            poa: block.poa.map(Into::into),
+            filtered: block.filtered,
+            usd_to_ar_rate: block.usd_to_ar_rate.map(TryInto::try_into).transpose()?,
+            scheduled_usd_to_ar_rate: block
+                .scheduled_usd_to_ar_rate
                 .map(TryInto::try_into)
-                .collect::<Result<Vec<_>>>()?,
-            reward_pool: Some(BigInt {
-                bytes: U256::from_dec_str(&block.reward_pool)?.to_be(),
-            }),
-            weave_size: Some(block.weave_size.try_into()?),
-            block_size: Some(block.block_size.try_into()?),
-            cumulative_diff: Some(block.cumulative_diff.try_into()?),
-            hash_list_merkle: block.hash_list_merkle.unwrap_or_default().into(),
-            poa: block.poa.and_then(|p| p.try_into().ok()),
+                .transpose()?,
+            packing_2_5_threshold: big_uint_since(Fork::V2_5, block.packing_2_5_threshold)?,
+            strict_data_split_threshold: big_uint_since(
+                Fork::V2_5,
+                block.strict_data_split_threshold,
+            )?,
+            hash_preimage: since(network, Fork::V2_6, height, block.hash_preimage.into()),
+            recall_byte: big_uint_since(Fork::V2_6, block.recall_byte)?,
+            reward: big_uint_since(Fork::V2_6, block.reward)?,
+            previous_solution_hash: since(
+                network,
+                Fork::V2_6,
+                height,
+                block.previous_solution_hash.into(),
+            ),
+            partition_number: since(network, Fork::V2_6, height, block.partition_number),
+            nonce_limiter_info: block
+                .nonce_limiter_info
+                .map(TryInto::try_into)
+                .transpose()?,
+            poa2: block.poa2.map(Into::into),
+            signature: since(network, Fork::V2_6, height, block.signature.into()),
+            reward_key: since(network, Fork::V2_6, height, block.reward_key.into()),
+            price_per_gib_minute: big_uint_since(Fork::V2_6, block.price_per_gib_minute)?,
+            scheduled_price_per_gib_minute: big_uint_since(
+                Fork::V2_6,
+                block.scheduled_price_per_gib_minute,
+            )?,
+            reward_history_hash: since(
+                network,
+                Fork::V2_6,
+                height,
+                block.reward_history_hash.into(),
+            ),
+            debt_supply: big_uint_since(Fork::V2_6, block.debt_supply)?,
+            kryder_plus_rate_multiplier: big_uint_since(
+                Fork::V2_6,
+                block.kryder_plus_rate_multiplier,
+            )?,
+            kryder_plus_rate_multiplier_latch: big_uint_since(
+                Fork::V2_6,
+                block.kryder_plus_rate_multiplier_latch,
+            )?,
+            denomination: big_uint_since(Fork::V2_6, block.denomination)?,
+            redenomination_height: since(network, Fork::V2_6, height, block.redenomination_height),
+            double_signing_proof: block
+                .double_signing_proof
+                .map(TryInto::try_into)
+                .transpose()?,
+            previous_cumulative_diff: big_uint_since(Fork::V2_6, block.previous_cumulative_diff)?,
+            merkle_rebase_support_threshold: big_uint_since(
+                Fork::V2_7,
+                block.merkle_rebase_support_threshold,
+            )?,
+            chunk_hash: since(network, Fork::V2_7, height, block.chunk_hash.into()),
+            // only set along `recall_byte2`, when the solution uses a second chunk
+            chunk2_hash: (!block.chunk2_hash.is_empty() || block.recall_byte2.is_some())
+                .then(|| block.chunk2_hash.into()),
+            block_time_history_hash: since(
+                network,
+                Fork::V2_7,
+                height,
+                block.block_time_history_hash.into(),
+            ),
+            recall_byte2: big_uint_since(Fork::V2_7, block.recall_byte2)?,
         })
     }
 }
 
-impl TryFrom<Poa> for ProofOfAccess {
+impl From<ProofOfAccess> for Poa {
+    fn from(poa: ProofOfAccess) -> Self {
+        Self {
+            option: poa.option,
+            tx_path: poa.tx_path.into(),
+            data_path: poa.data_path.into(),
+            chunk: poa.chunk.into(),
+        }
+    }
+}
+
+impl TryFrom<Rate> for types::Rate {
+    type Error = Error;
+
+    fn try_from(rate: Rate) -> Result<Self> {
+        Ok(Self(big_uint(rate.numerator)?, big_uint(rate.denominator)?))
+    }
+}
+
+impl TryFrom<NonceLimiterInfo> for types::NonceLimiterInfo {
     type Error = Error;
 
-    fn try_from(poa: Poa) -> Result<Self> {
+    fn try_from(info: NonceLimiterInfo) -> Result<Self> {
         Ok(Self {
-            option: poa.option,
-            tx_path: bd(&poa.tx_path)?,
-            data_path: bd(&poa.data_path)?,
-            chunk: bd(&poa.chunk)?,
+            output: info.output.into(),
+            global_step_number: info.global_step_number,
+            seed: info.seed.into(),
+            next_seed: info.next_seed.into(),
+            zone_upper_bound: big_uint(info.zone_upper_bound)?,
+            next_zone_upper_bound: big_uint(info.next_zone_upper_bound)?,
+            prev_output: info.prev_output.into(),
+            last_step_checkpoints: info
+                .last_step_checkpoints
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            checkpoints: info.checkpoints.into_iter().map(Into::into).collect(),
+            vdf_difficulty: (info.vdf_difficulty != 0).then_some(info.vdf_difficulty),
+            next_vdf_difficulty: (info.next_vdf_difficulty != 0)
+                .then_some(info.next_vdf_difficulty),
         })
     }
 }
 
-impl TryFrom<types::Transaction> for Transaction {
+impl TryFrom<DoubleSigningProof> for types::DoubleSigningProof {
     type Error = Error;
 
-    fn try_from(tx: types::Transaction) -> Result<Self> {
+    fn try_from(proof: DoubleSigningProof) -> Result<Self> {
         Ok(Self {
-            format: tx.format.unwrap_or_default(),
-            id: bd(&tx.id)?,
-            last_tx: bd(&tx.last_tx)?,
-            owner: bd(&tx.owner)?,
-            tags: tx
-                .tags
-                .into_iter()
-                .map(TryInto::try_into)
-                .collect::<Result<Vec<_>>>()?,
-            target: bd(&tx.target)?,
-            quantity: Some(tx.quantity.try_into()?),
-            data: bd(&tx.data)?,
-            data_size: Some(tx.data_size.try_into()?),
-            data_root: bd(&tx.data_root)?,
-            signature: bd(&tx.signature)?,
-            reward: Some(tx.reward.try_into()?),
+            pub_key: proof.pub_key.into(),
+            sig1: proof.sig1.into(),
+            cdiff1: big_uint(proof.cdiff1)?,
+            prev_cdiff1: big_uint(proof.prev_cdiff1)?,
+            preimage1: proof.preimage1.into(),
+            sig2: proof.sig2.into(),
+            cdiff2: big_uint(proof.cdiff2)?,
+            prev_cdiff2: big_uint(proof.prev_cdiff2)?,
+            preimage2: proof.preimage2.into(),
         })
     }
 }
 
-impl TryFrom<types::Tag> for Tag {
+impl TryFrom<Transaction> for types::Transaction {
     type Error = Error;
 
-    fn try_from(tag: types::Tag) -> Result<Self> {
+    fn try_from(tx: Transaction) -> Result<Self> {
         Ok(Self {
-            name: bd(&tag.name)?,
-            value: bd(&tag.value)?,
+            format: (tx.format != 0).then_some(tx.format),
+            data_status: match tx.data_status() {
+                DataStatus::Inline => types::DataStatus::Inline,
+                DataStatus::Fetched => types::DataStatus::Fetched,
+                DataStatus::Skipped => types::DataStatus::Skipped,
+            },
+            signature_status: match tx.signature_status() {
+                SignatureStatus::Unverified => types::SignatureStatus::Unverified,
+                SignatureStatus::Valid => types::SignatureStatus::Valid,
+                SignatureStatus::Invalid => types::SignatureStatus::Invalid,
+            },
+            id: tx.id.into(),
+            last_tx: tx.last_tx.into(),
+            owner: tx.owner.into(),
+            tags: tx.tags.into_iter().map(Into::into).collect(),
+            target: tx.target.into(),
+            quantity: big_uint(tx.quantity)?,
+            data: tx.data.into(),
+            data_size: big_uint(tx.data_size)?,
+            data_root: tx.data_root.into(),
+            signature: tx.signature.into(),
+            reward: big_uint(tx.reward)?,
         })
     }
 }
+
+impl From<Tag> for types::Tag {
+    fn from(tag: Tag) -> Self {
+        Self {
+            name: tag.name.into(),
+            value: tag.value.into(),
+        }
+    }
+}
+
+impl Block {
+    /// Arweave JSON of the block as served by `/block/height/{height}` on
+    /// `network`, bytes are base64url encoded, big integers are decimal
+    /// strings and an empty `reward_addr` is `unclaimed`
+    ///
+    /// ```rust
+    /// use thegarii::{network::Network, pb, types::{Block, FirehoseBlock}};
+    ///
+    /// let block: Block = serde_json::from_str(include_str!("../res/block_height_269512.json")).unwrap();
+    /// let mut block = FirehoseBlock::from(block);
+    /// block.reward_addr = Default::default();
+    ///
+    /// let mainnet = Network::mainnet();
+    /// let json = pb::Block::new(block.clone(), &mainnet).arweave_json(&mainnet).unwrap();
+    /// assert_eq!(json["reward_addr"], "unclaimed");
+    /// assert_eq!(json["diff"], block.diff.to_string());
+    /// ```
+    pub fn arweave_json(&self, network: &Network) -> Result<serde_json::Value> {
+        let block = self.clone().into_firehose(network)?;
+        Ok(serde_json::to_value(types::Block::from(&block))?)
+    }
+}
@@ -1,28 +1,52 @@
 use crate::types::FirehoseBlock;
 // Copyright 2021 ChainSafe Systems
 // SPDX-License-Identifier: LGPL-3.0-only
-use crate::{client::Client, env::Env, pb::Block, Error, Result};
+use crate::{
+    client::Client,
+    concurrency::Concurrency,
+    env::Env,
+    filter::Filter,
+    firehose,
+    health::Health,
+    metrics,
+    replay::{Replay, Source},
+    verify::{self, VerifyPolicy},
+    Error, Result,
+};
 use anyhow::Context;
-use base64::{engine::general_purpose, Engine as _};
-use futures::stream;
+use futures::stream::FuturesOrdered;
 use futures::StreamExt;
-use prost::Message;
 
 use std::path::{Path, PathBuf};
-use std::{fs, time::Duration};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::{
+    fs,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::Instrument;
 
 /// polling service
 pub struct Polling {
     last_processed_block_path: Box<PathBuf>,
-    batch: usize,
+    concurrency: Arc<Concurrency>,
     block_time: u64,
     client: Client,
     confirms: u64,
+    emitted_blocks: usize,
+    emitted_bytes: usize,
     end: Option<u64>,
+    filter: Option<Filter>,
     forever: bool,
+    health: Arc<Health>,
     latest: u64,
+    memory_budget: usize,
     ptr: u64,
     quiet: bool,
+    replay: Option<Replay>,
+    verify_txs: Option<VerifyPolicy>,
 }
 
 impl Polling {
@@ -32,15 +56,16 @@ impl Polling {
         end: Option<u64>,
         env: Env,
         forever: bool,
+        health: Arc<Health>,
         ptr: Option<String>,
         quiet: bool,
     ) -> Result<Self> {
-        let client = Client::new(
-            env.endpoints.clone(),
-            Duration::from_millis(env.timeout),
-            env.retry,
-        )?;
-        let batch = env.batch_blocks as usize;
+        let concurrency = Arc::new(Concurrency::new(
+            env.batch_blocks as usize,
+            env.min_batch_blocks as usize,
+            env.max_batch_blocks as usize,
+        ));
+        let client = Client::from_config(&env)?.with_concurrency(concurrency.clone());
 
         fs::create_dir_all(&data_directory).context(
             format_args!("unable to create data directory {}", &data_directory).to_string(),
@@ -51,15 +76,22 @@ impl Polling {
 
         let mut poller = Self {
             last_processed_block_path: Box::new(last_processed_block_path),
-            batch,
+            concurrency,
             block_time: env.block_time,
             confirms: env.confirms,
             client,
+            emitted_blocks: 0,
+            emitted_bytes: 0,
             end,
+            filter: None,
             forever,
+            health,
             latest: 0,
+            memory_budget: env.memory_budget as usize,
             ptr: 0,
             quiet,
+            replay: None,
+            verify_txs: env.verify_txs,
         };
 
         poller.initialize_start_ptr(ptr).await?;
@@ -67,6 +99,21 @@ impl Polling {
         Ok(poller)
     }
 
+    /// drop transactions not matching `filter` from emitted blocks
+    pub fn with_filter(mut self, filter: Option<Filter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// emit the blocks of `source` instead of fetching them, until its last
+    /// block
+    pub fn with_replay(mut self, source: Option<&Source>) -> Result<Self> {
+        self.replay = source
+            .map(|source| Replay::open(source, &self.client))
+            .transpose()?;
+        Ok(self)
+    }
+
     async fn initialize_start_ptr(&mut self, start_block_flag: Option<String>) -> Result<()> {
         self.ptr = match self.last_processed_block_path.exists() {
             true => self.start_ptr_from_state().await?,
@@ -100,13 +147,11 @@ impl Polling {
 
         content.parse::<u64>()
             .context(format_args!("content {} is not a valid u64 string value", &content).to_string(),
-        ).map(|value|  {
+        ).inspect(|value|  {
             log::info!(
                 "start block retrieved from last processed block state file, starting from block {}",
                 value
             );
-
-            value
         }).map_err(Into::into)
     }
 
@@ -115,86 +160,46 @@ impl Polling {
 
         self.latest_irreversible_block_num()
             .await
-            .map(|live_block| {
+            .inspect(|live_block| {
                 log::info!(
                     "start block explicitly provided, starting from live block {}",
                     live_block
                 );
-
-                live_block
             })
     }
 
     async fn start_ptr_from_flag_value(&self, value: &String) -> Result<u64> {
         value
             .parse::<u64>()
-            .map(|value| {
+            .inspect(|value| {
                 log::info!(
                     "start block explicitly provided, starting from block {}",
                     value
                 );
-
-                value
             })
             .context(format_args!("start {} is not a valid u64 string value", value).to_string())
             .map_err(Into::into)
     }
 
     /// Firehose init log to stdout
-    ///
-    /// FIRE INIT <VERSION> <BLOCK_TYPE_URL>
     fn firehose_init(&self) {
-        println!("FIRE INIT 1.0 sf.arweave.type.v1.Block");
+        println!("{}", firehose::INIT);
     }
 
-    /// Firehose log to stdout
-    ///
-    /// FIRE BLOCK <BLOCK_NUM> <BLOCK_HASH> <PARENT_NUM> <PARENT_HASH> <LIB> <TIMESTAMP> <ENCODED>
-    fn firehose_log(&self, mut b: FirehoseBlock) -> Result<()> {
-        let block_num = b.height;
-        let block_hash = base64_url::decode(&b.indep_hash)
-            .with_context(|| format!("invalid base64url indep_hash on block {}", block_num))?;
-        let parent_hash = base64_url::decode(&b.previous_block)
-            .with_context(|| format!("invalid base64url previous_block on block {}", block_num))?;
-        let timestamp = b.timestamp * 1000000000;
-
-        let parent_num = if b.previous_block.is_empty() {
-            0
-        } else {
-            block_num - 1
-        };
-
-        let lib = if block_num > self.confirms {
-            block_num - self.confirms
-        } else {
-            0
-        };
-
-        if block_num < 422250 {
-            b.tx_root = None;
-        }
-
-        let encoded: Block = b.try_into()?;
-        let block_payload = if self.quiet {
-            "<trimmed>".to_string()
-        } else {
-            general_purpose::STANDARD.encode(encoded.encode_to_vec())
-        };
-
+    /// Firehose block log to stdout
+    fn firehose_log(&self, b: FirehoseBlock) -> Result<()> {
         println!(
-            "FIRE BLOCK {} {} {} {} {} {} {}",
-            block_num,
-            hex::encode(block_hash),
-            parent_num,
-            hex::encode(parent_hash),
-            lib,
-            timestamp,
-            block_payload
+            "{}",
+            firehose::block_line(b, self.client.network(), self.confirms, self.quiet)?
         );
-
         Ok(())
     }
 
+    /// running average of the size of emitted blocks
+    fn block_size_estimate(&self) -> Option<usize> {
+        self.emitted_bytes.checked_div(self.emitted_blocks)
+    }
+
     /// poll blocks and write to stdout
     async fn poll(&mut self, blocks: Vec<u64>) -> Result<()> {
         if blocks.is_empty() {
@@ -203,29 +208,130 @@ impl Polling {
         }
 
         log::info!(
-            "polling from {} to {}",
+            "polling from {} to {} with {} blocks in flight",
             blocks.first().expect("non-empty"),
-            blocks.last().expect("non-empty")
+            blocks.last().expect("non-empty"),
+            self.concurrency.limit(),
         );
 
-        let mut tasks = stream::iter(
-            blocks
-                .into_iter()
-                .map(|block| self.client.get_firehose_block_by_height(block)),
-        )
-        .buffered(self.batch);
+        let client = &self.client;
+        let replaying = self.replay.is_some();
+        let verify_txs = self.verify_txs;
+        // bytes of fetched blocks waiting for being emitted, and reserved for
+        // blocks being fetched
+        let buffered = AtomicUsize::new(0);
+        let buffered = &buffered;
+        let mut blocks = blocks.into_iter();
+        let mut tasks = FuturesOrdered::new();
 
-        while let Some(item) = tasks.next().await {
-            let block = item?;
-            let height = block.height;
+        loop {
+            // top up in flight blocks to the current concurrency limit while
+            // the estimated size of the next block fits in the memory budget,
+            // a single block is fetched until the size of blocks is known
+            metrics::CONCURRENCY.set(self.concurrency.limit() as i64);
+            while tasks.len() < self.concurrency.limit() {
+                let estimate = self.block_size_estimate();
+                let reserved = match estimate {
+                    _ if tasks.is_empty() => estimate.unwrap_or_default(),
+                    Some(estimate)
+                        if buffered.load(Ordering::SeqCst) + estimate <= self.memory_budget =>
+                    {
+                        estimate
+                    }
+                    _ => break,
+                };
+
+                match blocks.next() {
+                    Some(block) => {
+                        buffered.fetch_add(reserved, Ordering::SeqCst);
+                        // replayed blocks are read in order, before being queued
+                        let replayed = self.replay.as_mut().map(|replay| replay.block(block));
+                        tasks.push_back(async move {
+                            let mut block = match replayed {
+                                Some(replayed) => replayed?,
+                                None => client.get_firehose_block_by_height(block).await?,
+                            };
+                            // verify before storing, invalid txs never reach the index
+                            if let Some(policy) = verify_txs {
+                                block = tokio::task::spawn_blocking(move || {
+                                    verify::verify_block(block, policy)
+                                })
+                                .await
+                                .context("verification task panicked")??;
+                            }
+                            if !replaying {
+                                client.store(&block)?;
+                            }
+                            // the reservation is replaced by the actual size
+                            let size = block.size();
+                            buffered.fetch_add(size, Ordering::SeqCst);
+                            let in_memory =
+                                buffered.fetch_sub(reserved, Ordering::SeqCst) - reserved;
+                            metrics::BUFFERED_BYTES.set(in_memory as i64);
+                            tracing::debug!(
+                                height = block.height,
+                                bytes = size,
+                                buffered = in_memory,
+                                "fetched block"
+                            );
+                            Ok::<_, Error>(block)
+                        })
+                    }
+                    None => break,
+                }
+            }
 
-            self.firehose_log(block)?;
+            let mut block = match tasks.next().await {
+                Some(item) => item?,
+                None => break,
+            };
+            let height = block.height;
+            let fork = block.fork(self.client.network());
+            let timestamp = block.timestamp;
+            let size = block.size();
+            self.emitted_blocks += 1;
+            self.emitted_bytes += size;
+            if let Some(filter) = &self.filter {
+                filter.apply(&mut block);
+            }
+            let txs = block.txs.len();
+            let in_memory = buffered.fetch_sub(size, Ordering::SeqCst) - size;
+            metrics::BUFFERED_BYTES.set(in_memory as i64);
+            self.concurrency.on_block();
+
+            let now = Instant::now();
+            let emit = tracing::info_span!(
+                "emit_block",
+                height,
+                fork = fork.map(tracing::field::display),
+                txs,
+                bytes = size
+            );
+            emit.in_scope(|| {
+                tracing::info!(
+                    buffered = in_memory,
+                    "block {} uses {} bytes with {} txs, {} bytes buffered",
+                    height,
+                    size,
+                    txs,
+                    in_memory,
+                );
+                self.firehose_log(block)
+            })?;
             // # Safty
             //
             // only update ptr after firehose_log has been emitted
             self.ptr = height + 1;
 
-            self.write_ptr().await?;
+            self.write_ptr().instrument(emit.clone()).await?;
+            self.health.on_advance(self.ptr);
+            self.record_emitted(timestamp, txs);
+            emit.in_scope(|| {
+                tracing::debug!(
+                    duration_ms = now.elapsed().as_millis() as u64,
+                    "emitted block"
+                )
+            });
 
             if let Some(end) = self.end {
                 if height == end {
@@ -253,13 +359,37 @@ impl Polling {
         Ok(())
     }
 
+    /// update metrics after a block has been emitted
+    fn record_emitted(&self, timestamp: u64, txs: usize) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        metrics::BLOCKS_EMITTED.inc();
+        metrics::TXS_EMITTED.inc_by(txs as u64);
+        metrics::PTR.set(self.ptr as i64);
+        metrics::LAG_BLOCKS.set((metrics::HEAD.get() - self.ptr as i64).max(0));
+        metrics::LAG_SECONDS.set(now.saturating_sub(timestamp) as i64);
+    }
+
     async fn latest_irreversible_block_num(&self) -> Result<u64> {
-        let head_block = self.client.get_current_block().await?.height;
-        if head_block < self.confirms {
-            return Ok(head_block);
-        }
+        // replayed blocks are all irreversible
+        let (head_block, lib) = match &self.replay {
+            Some(replay) => {
+                let last = replay.last_height()?.ok_or(Error::NoBlockExists)?;
+                (last, last)
+            }
+            None => {
+                let head_block = self.client.get_current_block().await?.height;
+                (head_block, head_block.saturating_sub(self.confirms))
+            }
+        };
+        metrics::HEAD.set(head_block as i64);
+        metrics::LIB.set(lib as i64);
+        self.health.on_head(head_block, lib);
 
-        Ok(head_block - self.confirms)
+        Ok(lib)
     }
 
     /// poll to head
@@ -274,9 +404,12 @@ impl Polling {
 
     /// start polling service
     pub async fn start(&mut self) -> Result<()> {
+        log::info!("polling {} blocks", self.client.network().name);
         self.firehose_init();
 
         loop {
+            self.health.on_progress();
+
             // restart when network error occurs
             let result = self.track_head().await;
             match result {
@@ -291,14 +424,21 @@ impl Polling {
                 Err(e) => {
                     log::error!("{:?}", e);
 
-                    if self.forever {
+                    // a replay source does not heal
+                    if self.forever && self.replay.is_none() {
                         log::info!("restarting...");
+                        metrics::RESTARTS.inc();
                         continue;
                     } else {
                         return Err(e);
                     }
                 }
 
+                _ if self.replay.is_some() => {
+                    log::info!("replayed blocks to {}, stopping poller", self.latest);
+                    return Ok(());
+                }
+
                 _ => {
                     log::info!(
                         "sleeping {}ms before checking for new blocks (last irrerversible block {})",
@@ -0,0 +1,238 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! replay of local blocks
+//!
+//! the console emits the blocks of a replay source instead of fetching them,
+//! history can be reprocessed without any network, with the same FIRE lines
+//! as live polling.
+#[cfg(feature = "full")]
+use crate::storage::Storage;
+use crate::{
+    archive::{self, ArchiveFile, Compression, Format},
+    network::Network,
+    types::{Block, FirehoseBlock, Transaction},
+    Client, Error, Result,
+};
+#[cfg(feature = "full")]
+use std::sync::Arc;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// blocks of a source in ascending height
+type Blocks = Box<dyn Iterator<Item = Result<FirehoseBlock>> + Send>;
+
+/// where replayed blocks come from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// archive written by `thegarii export`, `archive:<dir>`
+    Archive(PathBuf),
+    /// Arweave blocks in the shape of `res/`, `json:<dir>`, named
+    /// `block_height_{height}.json` with their txs in `tx/{id}.json`
+    Json(PathBuf),
+    /// the block database, `db`
+    #[cfg(feature = "full")]
+    Db,
+}
+
+impl FromStr for Source {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("archive", dir)) => Ok(Self::Archive(dir.into())),
+            Some(("json", dir)) => Ok(Self::Json(dir.into())),
+            #[cfg(feature = "full")]
+            None if s == "db" => Ok(Self::Db),
+            _ => Err(anyhow::anyhow!(
+                "invalid replay source {}, expect archive:<dir>, json:<dir> or db",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// source opened for replay
+enum Opened {
+    Archive {
+        files: Vec<ArchiveFile>,
+        format: Format,
+        compression: Compression,
+        network: Network,
+    },
+    /// heights and paths of the blocks, ascending
+    Json {
+        dir: PathBuf,
+        blocks: Vec<(u64, PathBuf)>,
+    },
+    #[cfg(feature = "full")]
+    Db(Arc<Storage>),
+}
+
+/// firehose block of a `res/` shaped block file, with its txs from `tx/`
+fn json_block(dir: &Path, path: &Path) -> Result<FirehoseBlock> {
+    let block: Block = serde_json::from_slice(&fs::read(path)?)?;
+    let txs = block
+        .txs
+        .iter()
+        .map(|id| {
+            let path = dir.join("tx").join(format!("{}.json", id));
+            let json = fs::read(&path).map_err(|_| Error::TxNotFound(id.to_string()))?;
+            Ok(serde_json::from_slice::<Transaction>(&json)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut block = FirehoseBlock::from(block);
+    block.txs = txs;
+    Ok(block)
+}
+
+/// sequential reader of the blocks of a replay source
+///
+/// ```rust
+/// use thegarii::{network::Network, replay::Replay, types::Block, Client};
+///
+/// let dir = std::env::temp_dir().join(format!("thegarii-replay-doctest-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+///
+/// // txs would be read from `tx/{id}.json`
+/// let mut block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+/// block.txs.clear();
+/// std::fs::write(dir.join("block_height_100.json"), serde_json::to_vec(&block).unwrap()).unwrap();
+///
+/// let endpoints = vec!["http://localhost:1984".into()];
+/// let client = Client::new(endpoints, Default::default(), 0, Network::mainnet()).unwrap();
+/// let mut replay = Replay::open(&format!("json:{}", dir.display()).parse().unwrap(), &client).unwrap();
+/// assert_eq!(replay.last_height().unwrap(), Some(100));
+/// assert_eq!(replay.block(100).unwrap().height, 100);
+/// assert!(replay.block(101).is_err());
+///
+/// drop(replay);
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct Replay {
+    opened: Opened,
+    /// height of the next block of `blocks`
+    cursor: Option<(u64, Blocks)>,
+}
+
+impl Replay {
+    /// open `source`, of the network of `client`, the block database is the
+    /// one of `client`
+    pub fn open(source: &Source, client: &Client) -> Result<Self> {
+        let opened = match source {
+            Source::Archive(dir) => {
+                let (format, compression) = archive::detect(dir)?;
+                Opened::Archive {
+                    files: archive::files(dir, format, compression)?,
+                    format,
+                    compression,
+                    network: client.network().clone(),
+                }
+            }
+            Source::Json(dir) => {
+                let mut blocks = vec![];
+                for entry in fs::read_dir(dir)? {
+                    let path = entry?.path();
+                    let height = path
+                        .file_name()
+                        .and_then(|name| name.to_str()?.strip_prefix("block_height_"))
+                        .and_then(|name| name.strip_suffix(".json")?.parse().ok());
+                    if let Some(height) = height {
+                        blocks.push((height, path));
+                    }
+                }
+                blocks.sort();
+
+                Opened::Json {
+                    dir: dir.clone(),
+                    blocks,
+                }
+            }
+            #[cfg(feature = "full")]
+            Source::Db => Opened::Db(
+                client
+                    .storage()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("no block database to replay"))?,
+            ),
+        };
+
+        Ok(Self {
+            opened,
+            cursor: None,
+        })
+    }
+
+    /// height of the last block of the source
+    pub fn last_height(&self) -> Result<Option<u64>> {
+        Ok(match &self.opened {
+            Opened::Archive { files, .. } => files.last().map(|file| file.last),
+            Opened::Json { blocks, .. } => blocks.last().map(|(height, _)| *height),
+            #[cfg(feature = "full")]
+            Opened::Db(storage) => storage.last_height()?,
+        })
+    }
+
+    /// blocks of the source from `start`
+    fn blocks_from(&self, start: u64) -> Result<Blocks> {
+        Ok(match &self.opened {
+            Opened::Archive {
+                files,
+                format,
+                compression,
+                network,
+            } => {
+                let network = network.clone();
+                let (format, compression) = (*format, *compression);
+                let files = files
+                    .iter()
+                    .filter(|file| file.last >= start)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                Box::new(
+                    files
+                        .into_iter()
+                        .flat_map(move |file| {
+                            match archive::blocks(&file, format, compression, network.clone()) {
+                                Ok(blocks) => Box::new(blocks) as Blocks,
+                                Err(e) => Box::new(std::iter::once(Err(e))),
+                            }
+                        })
+                        .filter(move |block| !matches!(block, Ok(b) if b.height < start)),
+                )
+            }
+            Opened::Json { dir, blocks } => {
+                let dir = dir.clone();
+                let blocks = blocks
+                    .iter()
+                    .filter(|(height, _)| *height >= start)
+                    .map(|(_, path)| path.clone())
+                    .collect::<Vec<_>>();
+                Box::new(blocks.into_iter().map(move |path| json_block(&dir, &path)))
+            }
+            #[cfg(feature = "full")]
+            Opened::Db(storage) => Box::new(storage.blocks_from(start)),
+        })
+    }
+
+    /// block at `height`, reading on from the previous one when consecutive
+    pub fn block(&mut self, height: u64) -> Result<FirehoseBlock> {
+        let mut blocks = match self.cursor.take() {
+            Some((next, blocks)) if next == height => blocks,
+            _ => self.blocks_from(height)?,
+        };
+
+        match blocks.next().transpose()? {
+            Some(block) if block.height == height => {
+                self.cursor = Some((height + 1, blocks));
+                Ok(block)
+            }
+            _ => Err(Error::BlockNotFound(height)),
+        }
+    }
+}
@@ -3,7 +3,10 @@
 
 //! the garii results
 
-use std::{convert::Infallible, env::VarError, net::AddrParseError, num::ParseIntError};
+use std::{
+    convert::Infallible, env::VarError, net::AddrParseError, num::ParseIntError,
+    str::ParseBoolError,
+};
 
 /// the garii errors
 #[derive(thiserror::Error, Debug)]
@@ -12,10 +15,26 @@ pub enum Error {
     BlockNotFound(u64),
     #[error("no endpoints provided")]
     EmptyEndpoints,
+    #[error(
+        "invalid {field}{} in {path}: {source}",
+        .height.map(|height| format!(" of block {}", height)).unwrap_or_default()
+    )]
+    InvalidField {
+        field: String,
+        height: Option<u64>,
+        path: String,
+        source: serde_json::Error,
+    },
     #[error("invalid path")]
     InvalidPath,
+    #[error("invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("invalid firehose payload: {0}")]
+    InvalidPayload(String),
     #[error("invalid block range")]
     InvalidRange,
+    #[error("invalid tx {id}: {reason}")]
+    InvalidTx { id: String, reason: String },
     #[error("invalid timestamp")]
     InvalidTimestamp,
     #[error("no block exists")]
@@ -32,6 +51,16 @@ pub enum Error {
     StopBlockReached,
     #[error("retries reached")]
     RetriesReached,
+    #[error("tx {0} not found")]
+    TxNotFound(String),
+    #[error("txs of block {height} do not match it: {reason}")]
+    TxsMismatch { height: u64, reason: String },
+    #[error("{missing} txs of block {height} could not be fetched: {source}")]
+    TxsUnavailable {
+        height: u64,
+        missing: usize,
+        source: Box<Error>,
+    },
     #[error(transparent)]
     AddrParseError(#[from] AddrParseError),
     #[error(transparent)]
@@ -43,11 +72,16 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
+    ParseBool(#[from] ParseBoolError),
+    #[error(transparent)]
     ParseInt(#[from] ParseIntError),
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
+    #[cfg(feature = "full")]
+    #[error(transparent)]
+    Sled(#[from] sled::Error),
     #[error(transparent)]
     Timestamp(#[from] prost_types::TimestampError),
     #[error(transparent)]
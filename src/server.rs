@@ -0,0 +1,60 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! minimal http server for the local endpoints
+use crate::Result;
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use tokio::net::TcpListener;
+
+/// build a response with status, content type and body
+pub fn response(
+    status: StatusCode,
+    content_type: &str,
+    body: impl Into<Bytes>,
+) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        content_type.parse().expect("valid content type"),
+    );
+    response
+}
+
+/// serve requests on `addr` with `handler` until the listener fails
+pub async fn serve<F>(addr: SocketAddr, handler: F) -> Result<()>
+where
+    F: Fn(Request<Incoming>) -> Response<Full<Bytes>> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr).await?;
+    let handler = Arc::new(handler);
+    log::info!("serving http on {}", addr);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let handler = handler.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(|request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response) }
+            });
+
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                log::debug!("http connection error: {:?}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! local block database, enabled by the `full` feature
+//!
+//! an embedded sled database of irreversible firehose blocks, keyed by
+//! height, with an index from block hashes to heights and an optional index
+//! from transaction ids to their location. blocks are stored as JSON since
+//! some fields are decoded from either numbers or strings.
+use crate::{types::FirehoseBlock, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, Weak},
+};
+
+/// firehose blocks by big endian height
+const BLOCKS: &str = "blocks";
+/// heights by block `indep_hash`
+const HASHES: &str = "hashes";
+/// locations of transactions by id
+const TXS: &str = "txs";
+
+/// databases opened by this process, sled locks the database directory
+static OPENED: LazyLock<Mutex<HashMap<PathBuf, Weak<Storage>>>> = LazyLock::new(Default::default);
+
+/// location of a transaction in the weave
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxLocation {
+    /// height of the block containing the transaction
+    pub height: u64,
+    /// `indep_hash` of the block containing the transaction
+    pub indep_hash: String,
+    /// position of the transaction in the block
+    pub position: u32,
+}
+
+/// local block database
+///
+/// ```rust
+/// use thegarii::{storage::Storage, types::FirehoseBlock};
+///
+/// let dir = std::env::temp_dir().join(format!("thegarii-storage-doctest-{}", std::process::id()));
+/// let storage = Storage::open(&dir).unwrap();
+///
+/// let block: FirehoseBlock = serde_json::from_str::<thegarii::types::Block>(
+///     include_str!("../res/block_height_100.json"),
+/// )
+/// .unwrap()
+/// .into();
+/// storage.put_block(&block).unwrap();
+///
+/// assert_eq!(storage.get_block(100).unwrap(), Some(block.clone()));
+/// assert_eq!(storage.get_height(&block.indep_hash.to_string()).unwrap(), Some(100));
+///
+/// // the same database is shared within the process
+/// assert!(Storage::open(&dir).unwrap().get_block(100).unwrap().is_some());
+///
+/// // transactions are located once their block is indexed
+/// let mut block = block;
+/// block.height = 101;
+/// block.indep_hash = b"101".to_vec().into();
+/// block.txs = vec![serde_json::from_str(include_str!("../res/tx.json")).unwrap()];
+/// storage.put_block(&block).unwrap();
+/// storage.index_txs(&block).unwrap();
+///
+/// let location = storage.lookup_tx(&block.txs[0].id.to_string()).unwrap().unwrap();
+/// assert_eq!((location.height, location.position), (101, 0));
+///
+/// storage.rebuild_tx_index().unwrap();
+/// assert_eq!(storage.lookup_tx(&block.txs[0].id.to_string()).unwrap(), Some(location));
+///
+/// drop(storage);
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Storage {
+    path: PathBuf,
+    blocks: sled::Tree,
+    hashes: sled::Tree,
+    txs: sled::Tree,
+    db: sled::Db,
+}
+
+impl Storage {
+    /// open or create the database at `path`, returns the opened one if
+    /// this process already holds it
+    pub fn open(path: impl AsRef<Path>) -> Result<Arc<Self>> {
+        let path = path.as_ref().to_path_buf();
+        let mut opened = OPENED.lock().expect("opened databases poisoned");
+        if let Some(storage) = opened.get(&path).and_then(Weak::upgrade) {
+            return Ok(storage);
+        }
+
+        log::info!("opening block database {:?}", path);
+        let db = sled::open(&path).map_err(|e| {
+            anyhow::anyhow!(
+                "unable to open block database {:?}, a single process can open it at a time: {}",
+                path,
+                e
+            )
+        })?;
+        let storage = Arc::new(Self {
+            path: path.clone(),
+            blocks: db.open_tree(BLOCKS)?,
+            hashes: db.open_tree(HASHES)?,
+            txs: db.open_tree(TXS)?,
+            db,
+        });
+
+        opened.insert(path, Arc::downgrade(&storage));
+        Ok(storage)
+    }
+
+    /// path of this database
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// get block by height
+    pub fn get_block(&self, height: u64) -> Result<Option<FirehoseBlock>> {
+        self.blocks
+            .get(height.to_be_bytes())?
+            .map(|value| serde_json::from_slice(&value).map_err(Into::into))
+            .transpose()
+    }
+
+    /// get the height of the block with `indep_hash`
+    pub fn get_height(&self, indep_hash: &str) -> Result<Option<u64>> {
+        Ok(self
+            .hashes
+            .get(indep_hash)?
+            .and_then(|value| value.as_ref().try_into().ok().map(u64::from_be_bytes)))
+    }
+
+    /// get block by `indep_hash`
+    pub fn get_block_by_hash(&self, indep_hash: &str) -> Result<Option<FirehoseBlock>> {
+        match self.get_height(indep_hash)? {
+            Some(height) => self.get_block(height),
+            None => Ok(None),
+        }
+    }
+
+    /// store an irreversible block, replaces the block stored at the same height
+    pub fn put_block(&self, block: &FirehoseBlock) -> Result<()> {
+        let key = block.height.to_be_bytes();
+        if let Some(previous) = self.get_block(block.height)? {
+            if previous == *block {
+                return Ok(());
+            }
+            if previous.indep_hash != block.indep_hash {
+                self.hashes.remove(previous.indep_hash.to_string())?;
+                self.unindex_txs(&previous)?;
+            }
+        }
+
+        self.blocks.insert(key, serde_json::to_vec(block)?)?;
+        self.hashes.insert(block.indep_hash.to_string(), &key)?;
+        Ok(())
+    }
+
+    /// locate transaction by id
+    pub fn lookup_tx(&self, id: &str) -> Result<Option<TxLocation>> {
+        self.txs
+            .get(id)?
+            .map(|value| bincode::deserialize(&value).map_err(Into::into))
+            .transpose()
+    }
+
+    /// index the transactions of a block
+    pub fn index_txs(&self, block: &FirehoseBlock) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (position, tx) in block.txs.iter().enumerate() {
+            let location = TxLocation {
+                height: block.height,
+                indep_hash: block.indep_hash.to_string(),
+                position: position as u32,
+            };
+            batch.insert(tx.id.to_string().as_bytes(), bincode::serialize(&location)?);
+        }
+
+        self.txs.apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// drop the index entries pointing to `block`
+    fn unindex_txs(&self, block: &FirehoseBlock) -> Result<()> {
+        let indep_hash = block.indep_hash.to_string();
+        for tx in &block.txs {
+            let id = tx.id.to_string();
+            if let Some(location) = self.lookup_tx(&id)? {
+                if location.indep_hash == indep_hash {
+                    self.txs.remove(id.as_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// rebuild the transaction index from stored blocks, returns the number
+    /// of indexed transactions
+    pub fn rebuild_tx_index(&self) -> Result<u64> {
+        self.txs.clear()?;
+
+        let mut indexed = 0;
+        for block in self.blocks_from(0) {
+            let block = block?;
+            if block.filtered {
+                log::warn!("block {} is filtered, its index is partial", block.height);
+            }
+
+            self.index_txs(&block)?;
+            indexed += block.txs.len() as u64;
+        }
+
+        self.flush()?;
+        Ok(indexed)
+    }
+
+    /// if no transaction has been indexed
+    pub fn tx_index_is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    /// if some stored block holds transactions, scanning from the lowest
+    pub fn has_txs(&self) -> Result<bool> {
+        for block in self.blocks_from(0) {
+            if !block?.txs.is_empty() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// number of stored blocks
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// if no block has been stored
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// highest stored height
+    pub fn last_height(&self) -> Result<Option<u64>> {
+        Ok(self
+            .blocks
+            .last()?
+            .and_then(|(key, _)| key.as_ref().try_into().ok().map(u64::from_be_bytes)))
+    }
+
+    /// iterate stored blocks from `start` in ascending height
+    pub fn blocks_from(&self, start: u64) -> impl Iterator<Item = Result<FirehoseBlock>> {
+        self.blocks
+            .range(start.to_be_bytes()..)
+            .values()
+            .map(|value| Ok(serde_json::from_slice(&value?)?))
+    }
+
+    /// flush pending writes to disk
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
@@ -2,91 +2,465 @@
 // SPDX-License-Identifier: LGPL-3.0-only
 
 //! arweave types
-use crate::encoding::{number_or_string, option_number_or_string};
-use serde::{Deserialize, Serialize};
+use crate::{
+    encoding::{empty_object_as_none, number_or_string},
+    network::{Fork, Network},
+    Error,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, ops::Deref, str::FromStr};
+
+/// bytes, encoded with base64url in JSON and decoded once deserialized
+///
+/// ```rust
+/// use thegarii::types::Base64Url;
+///
+/// let bytes: Base64Url = serde_json::from_str("\"dGhlZ2FyaWk\"").unwrap();
+/// assert_eq!(&bytes[..], b"thegarii");
+/// assert_eq!(bytes.to_string(), "dGhlZ2FyaWk");
+///
+/// let error = serde_json::from_str::<Base64Url>("\"the garii\"").unwrap_err();
+/// assert!(error.to_string().starts_with("invalid base64url \"the garii\""));
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Base64Url(pub Vec<u8>);
+
+impl Base64Url {
+    /// the decoded bytes
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for Base64Url {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Base64Url {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Base64Url> for Vec<u8> {
+    fn from(bytes: Base64Url) -> Self {
+        bytes.0
+    }
+}
+
+impl FromStr for Base64Url {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(Self(base64_url::decode(s)?))
+    }
+}
+
+impl fmt::Display for Base64Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&base64_url::encode(&self.0))
+    }
+}
+
+impl fmt::Debug for Base64Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl Serialize for Base64Url {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64Url {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|e| {
+            // payloads may be megabytes
+            let shown = s.char_indices().nth(64).map_or(&s[..], |(i, _)| &s[..i]);
+            de::Error::custom(format!("invalid base64url {:?}: {}", shown, e))
+        })
+    }
+}
+
+/// unsigned integer up to 256 bits, a number or a decimal string in JSON
+///
+/// ```rust
+/// use thegarii::types::BigUint;
+///
+/// let n: BigUint = serde_json::from_str("31").unwrap();
+/// assert_eq!(n, serde_json::from_str("\"31\"").unwrap());
+/// assert_eq!(serde_json::to_string(&n).unwrap(), "\"31\"");
+/// assert!(serde_json::from_str::<BigUint>("\"0x1f\"").is_err());
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigUint(pub U256);
+
+/// amount of AR in Winstons, 10^12 Winstons make an AR
+pub type Winston = BigUint;
+
+impl BigUint {
+    /// the value if it fits in a `u64`
+    pub fn to_u64(&self) -> Option<u64> {
+        (self.0 <= U256::from(u64::MAX)).then(|| self.0.low_u64())
+    }
+
+    /// if the value is zero
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        Self(n.into())
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(Self(U256::from_dec_str(s)?))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for BigUint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BigUint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = number_or_string(deserializer)?;
+        s.parse()
+            .map_err(|e| de::Error::custom(format!("invalid decimal {:?}: {}", s, e)))
+    }
+}
 
 /// Arweave Block
 ///
-/// - block height < 269510
-/// - 269510 <= block height < 422250
-/// - 422250 < block height
+/// fields follow the forks of the network, fields of later forks
+/// are `None` before them
+///
+/// - before the 1.8 fork
+/// - since the 1.8 fork, string difficulties and `cumulative_diff`
+/// - since the 2.0 fork, `tx_root`
+/// - 2.5, 2.6 and 2.7 forks
 ///
 /// # NOTE
 ///
 /// Arweave encoding their data with Base64URL, see
 /// https://docs.arweave.org/developers/server/http-api#transaction-format,
-/// here we simply parse `String` wrt the golang arweave client implementation
-/// https://github.com/everFinance/goar/blob/main/types/block.go
-///
-/// ## TODO
-///
-/// Convert `String` to `Vec<u8>` for more effcient
+/// bytes are decoded into `Base64Url` and numbers into `BigUint` while
+/// deserializing
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Block {
-    // - block height < 269510
-    pub nonce: String,
-    pub previous_block: String,
+    // - before the 1.8 fork
+    pub nonce: Base64Url,
+    pub previous_block: Base64Url,
     pub timestamp: u64,
     pub last_retarget: u64,
-    // - `u64` if block height < 269510
-    // - `String` if block height >= 269510
-    #[serde(deserialize_with = "number_or_string")]
-    pub diff: String,
+    // - `u64` before the 1.8 fork
+    // - `String` since the 1.8 fork
+    pub diff: BigUint,
     pub height: u64,
-    pub hash: String,
-    pub indep_hash: String,
-    pub txs: Vec<String>,
-    pub wallet_list: String,
-    pub reward_addr: String,
+    pub hash: Base64Url,
+    pub indep_hash: Base64Url,
+    pub txs: Vec<Base64Url>,
+    pub wallet_list: Base64Url,
+    /// empty if `unclaimed`
+    #[serde(with = "crate::encoding::unclaimed")]
+    pub reward_addr: Base64Url,
     pub tags: Vec<Tag>,
-    #[serde(deserialize_with = "number_or_string")]
-    pub reward_pool: String,
-    #[serde(deserialize_with = "number_or_string")]
-    pub weave_size: String,
-    #[serde(deserialize_with = "number_or_string")]
-    pub block_size: String,
-    // - 269510 <= block height < 422250
+    pub reward_pool: Winston,
+    pub weave_size: BigUint,
+    pub block_size: BigUint,
+    // - since the 1.8 fork
     #[serde(default)]
-    #[serde(deserialize_with = "option_number_or_string")]
-    pub cumulative_diff: Option<String>,
-    pub hash_list_merkle: Option<String>,
-    // - block height > 422250
-    pub tx_root: Option<String>,
-    pub tx_tree: Option<Vec<String>>,
+    pub cumulative_diff: Option<BigUint>,
+    pub hash_list_merkle: Option<Base64Url>,
+    // - since the 2.0 fork
+    pub tx_root: Option<Base64Url>,
+    pub tx_tree: Option<Vec<Base64Url>>,
     pub poa: Option<Poa>,
+    // - since the 2.5 fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_to_ar_rate: Option<Rate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_usd_to_ar_rate: Option<Rate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packing_2_5_threshold: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_data_split_threshold: Option<BigUint>,
+    // - since the 2.6 fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_preimage: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recall_byte: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Winston>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_solution_hash: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce_limiter_info: Option<NonceLimiterInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poa2: Option<Poa>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_key: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_per_gib_minute: Option<Winston>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_price_per_gib_minute: Option<Winston>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_history_hash: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debt_supply: Option<Winston>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kryder_plus_rate_multiplier: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kryder_plus_rate_multiplier_latch: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denomination: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redenomination_height: Option<u64>,
+    /// `{}` if no double signing has been reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "empty_object_as_none")]
+    pub double_signing_proof: Option<DoubleSigningProof>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_cumulative_diff: Option<BigUint>,
+    // - since the 2.7 fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_rebase_support_threshold: Option<BigUint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_hash: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk2_hash: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time_history_hash: Option<Base64Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recall_byte2: Option<BigUint>,
 }
 
 /// POA field of `Block`
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Poa {
     pub option: String,
-    pub tx_path: String,
-    pub data_path: String,
-    pub chunk: String,
+    pub tx_path: Base64Url,
+    pub data_path: Base64Url,
+    pub chunk: Base64Url,
+}
+
+/// price of AR in USD as a fraction, `[numerator, denominator]`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Rate(pub BigUint, pub BigUint);
+
+/// verifiable delay function state of `Block` since 2.6
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NonceLimiterInfo {
+    /// output of the last step
+    pub output: Base64Url,
+    /// number of steps computed since the 2.6 fork
+    pub global_step_number: u64,
+    /// seed of the current partition upper bound
+    pub seed: Base64Url,
+    /// seed of the next one
+    pub next_seed: Base64Url,
+    /// upper bound of the weave offsets which can be mined
+    pub zone_upper_bound: BigUint,
+    /// upper bound once `next_seed` applies
+    pub next_zone_upper_bound: BigUint,
+    /// output of the previous block
+    pub prev_output: Base64Url,
+    /// checkpoints of the last step
+    pub last_step_checkpoints: Vec<Base64Url>,
+    /// outputs of the steps since the previous block
+    pub checkpoints: Vec<Base64Url>,
+    // - since the 2.7 fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vdf_difficulty: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_vdf_difficulty: Option<u64>,
+}
+
+/// proof of a mining address signing two blocks at the same height
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DoubleSigningProof {
+    pub pub_key: Base64Url,
+    pub sig1: Base64Url,
+    pub cdiff1: BigUint,
+    pub prev_cdiff1: BigUint,
+    pub preimage1: Base64Url,
+    pub sig2: Base64Url,
+    pub cdiff2: BigUint,
+    pub prev_cdiff2: BigUint,
+    pub preimage2: Base64Url,
 }
 
 /// Transaction type
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Transaction {
     pub format: Option<u32>,
-    pub id: String,
-    pub last_tx: String,
-    pub owner: String,
+    pub id: Base64Url,
+    pub last_tx: Base64Url,
+    pub owner: Base64Url,
     pub tags: Vec<Tag>,
-    pub target: String,
-    pub quantity: String,
-    pub data_root: String,
-    pub data: String,
-    pub data_size: String,
-    pub reward: String,
-    pub signature: String,
+    pub target: Base64Url,
+    pub quantity: Winston,
+    pub data_root: Base64Url,
+    pub data: Base64Url,
+    pub data_size: BigUint,
+    pub reward: Winston,
+    pub signature: Base64Url,
+    /// How `data` has been retrieved
+    #[serde(default, skip_serializing_if = "DataStatus::is_inline")]
+    pub data_status: DataStatus,
+    /// Outcome of verifying `signature` and `id`
+    #[serde(default, skip_serializing_if = "SignatureStatus::is_unverified")]
+    pub signature_status: SignatureStatus,
+}
+
+/// How `Transaction::data` has been retrieved
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataStatus {
+    /// As returned by `/tx/{id}`, the payload of format 1 transactions,
+    /// usually empty for format 2 ones
+    #[default]
+    Inline,
+    /// The payload of a format 2 transaction has been fetched
+    Fetched,
+    /// The payload of a format 2 transaction exists but has not been fetched
+    Skipped,
+}
+
+impl DataStatus {
+    /// if `data` is as returned by `/tx/{id}`
+    pub fn is_inline(&self) -> bool {
+        *self == Self::Inline
+    }
+}
+
+/// Outcome of verifying `Transaction::signature` and `Transaction::id`
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// Not verified, or carrying an ECDSA signature of an empty `owner`
+    #[default]
+    Unverified,
+    /// Signed by `owner` and identified by the hash of its signature
+    Valid,
+    /// Either the signature or the id does not match the transaction
+    Invalid,
+}
+
+impl SignatureStatus {
+    /// if the transaction has not been verified
+    pub fn is_unverified(&self) -> bool {
+        *self == Self::Unverified
+    }
+}
+
+impl Poa {
+    /// approximate heap size in bytes
+    pub fn size(&self) -> usize {
+        self.option.len() + self.tx_path.len() + self.data_path.len() + self.chunk.len()
+    }
+}
+
+impl NonceLimiterInfo {
+    /// approximate heap size in bytes
+    pub fn size(&self) -> usize {
+        self.output.len()
+            + self.seed.len()
+            + self.next_seed.len()
+            + self.prev_output.len()
+            + self
+                .last_step_checkpoints
+                .iter()
+                .map(|c| c.len())
+                .sum::<usize>()
+            + self.checkpoints.iter().map(|c| c.len()).sum::<usize>()
+    }
+}
+
+impl DoubleSigningProof {
+    /// approximate heap size in bytes
+    pub fn size(&self) -> usize {
+        self.pub_key.len()
+            + self.sig1.len()
+            + self.preimage1.len()
+            + self.sig2.len()
+            + self.preimage2.len()
+    }
+}
+
+impl Transaction {
+    /// the transaction as returned by `/tx/{id}`, fetched payloads and
+    /// verification outcomes are dropped
+    pub fn as_returned(&self) -> Self {
+        let mut tx = self.clone();
+        tx.signature_status = SignatureStatus::Unverified;
+        if tx.data_status != DataStatus::Inline {
+            tx.data = Base64Url::default();
+            tx.data_status = DataStatus::Inline;
+        }
+        tx
+    }
+
+    /// approximate heap size in bytes
+    pub fn size(&self) -> usize {
+        self.id.len()
+            + self.last_tx.len()
+            + self.owner.len()
+            + self.tags.iter().map(Tag::size).sum::<usize>()
+            + self.target.len()
+            + self.data_root.len()
+            + self.data.len()
+            + self.signature.len()
+    }
+}
+
+/// status of a transaction as returned by `/tx/{id}/status`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxStatus {
+    /// height of the block containing the transaction
+    pub block_height: u64,
+    /// `indep_hash` of the block containing the transaction
+    pub block_indep_hash: Base64Url,
+    /// blocks mined on top of the block, including it
+    pub number_of_confirmations: u64,
 }
 
 /// Transaction type
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Tag {
-    pub name: String,
-    pub value: String,
+    pub name: Base64Url,
+    pub value: Base64Url,
 }
 
 /// abstract firehose block which simply combines
@@ -96,51 +470,217 @@ pub struct FirehoseBlock {
     /// Firehose block version (unrelated to Arweave block version)
     pub ver: u32,
     /// The block identifier
-    pub indep_hash: String,
+    pub indep_hash: Base64Url,
     /// The nonce chosen to solve the mining problem
-    pub nonce: String,
+    pub nonce: Base64Url,
     /// `indep_hash` of the previous block in the weave
-    pub previous_block: String,
+    pub previous_block: Base64Url,
     /// POSIX time of block discovery
     pub timestamp: u64,
     /// POSIX time of the last difficulty retarget
     pub last_retarget: u64,
     /// Mining difficulty, the number `hash` must be greater than.
-    pub diff: String,
+    pub diff: BigUint,
     /// How many blocks have passed since the genesis block
     pub height: u64,
     /// Mining solution hash of the block, must satisfy the mining difficulty
-    pub hash: String,
+    pub hash: Base64Url,
     /// Merkle root of the tree of Merkle roots of block's transactions' data.
-    pub tx_root: Option<String>,
+    pub tx_root: Option<Base64Url>,
     /// Transactions contained within this block
     pub txs: Vec<Transaction>,
     /// The root hash of the Merkle Patricia Tree containing
     /// all wallet (account) balances and the identifiers
     /// of the last transactions posted by them, if any.
-    pub wallet_list: String,
+    pub wallet_list: Base64Url,
     /// Address of the account to receive the block rewards. Can also be unclaimed which is encoded as a null byte
-    pub reward_addr: String,
+    #[serde(with = "crate::encoding::unclaimed")]
+    pub reward_addr: Base64Url,
     /// Tags that a block producer can add to a block
     pub tags: Vec<Tag>,
     /// Size of reward pool
-    pub reward_pool: String,
+    pub reward_pool: Winston,
     /// Size of the weave in bytes
-    pub weave_size: String,
+    pub weave_size: BigUint,
     /// Size of this block in bytes
-    pub block_size: String,
+    pub block_size: BigUint,
     /// Required after the version 1.8 fork. Zero otherwise.
     /// The sum of the average number of hashes computed
     /// by the network to produce the past blocks including this one.
-    pub cumulative_diff: Option<String>,
+    pub cumulative_diff: Option<BigUint>,
     // // The list of the block identifiers of the last
     // // STORE_BLOCKS_BEHIND_CURRENT blocks.
     // pub hash_list: Vec<String>,
     // Required after the version 1.8 fork. Null byte otherwise.
     // The Merkle root of the block index - the list of {`indep_hash`, `weave_size`, `tx_root`} triplets
-    pub hash_list_merkle: Option<String>,
+    pub hash_list_merkle: Option<Base64Url>,
     // The proof of access, Used after v2.4 only, set as defaults otherwise
     pub poa: Option<Poa>,
+    /// If transactions have been filtered out, `txs` is partial if set
+    #[serde(default)]
+    pub filtered: bool,
+    /// USD to AR rate of the pricing, since 2.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_to_ar_rate: Option<Rate>,
+    /// USD to AR rate applying in the next pricing window, since 2.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_usd_to_ar_rate: Option<Rate>,
+    /// Weave offset from which chunks are packed with the 2.5 scheme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packing_2_5_threshold: Option<BigUint>,
+    /// Weave offset from which chunks are split strictly, since 2.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_data_split_threshold: Option<BigUint>,
+    /// Mining solution preimage, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_preimage: Option<Base64Url>,
+    /// Weave offset of the recall chunk, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recall_byte: Option<BigUint>,
+    /// Reward of the miner in Winstons, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Winston>,
+    /// Mining solution hash of the previous block, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_solution_hash: Option<Base64Url>,
+    /// Storage partition the recall chunk belongs to, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_number: Option<u64>,
+    /// Verifiable delay function state, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce_limiter_info: Option<NonceLimiterInfo>,
+    /// Proof of access to the second recall chunk, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poa2: Option<Poa>,
+    /// Signature of the block by the miner, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Base64Url>,
+    /// Public key of the miner, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_key: Option<Base64Url>,
+    /// Price of storing a GiB for a minute in Winstons, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_per_gib_minute: Option<Winston>,
+    /// Price applying in the next pricing window, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_price_per_gib_minute: Option<Winston>,
+    /// Hash of the recent block rewards, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_history_hash: Option<Base64Url>,
+    /// Winstons taken from the endowment on credit, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debt_supply: Option<Winston>,
+    /// Multiplier of the Kryder+ rate, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kryder_plus_rate_multiplier: Option<BigUint>,
+    /// Latch of the Kryder+ rate multiplier, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kryder_plus_rate_multiplier_latch: Option<BigUint>,
+    /// Denomination of the Winston amounts, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denomination: Option<BigUint>,
+    /// Height of the last redenomination, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redenomination_height: Option<u64>,
+    /// Proof of a miner signing two blocks at the same height, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub double_signing_proof: Option<DoubleSigningProof>,
+    /// `cumulative_diff` of the previous block, since 2.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_cumulative_diff: Option<BigUint>,
+    /// Weave offset from which the Merkle rebase applies, since 2.7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_rebase_support_threshold: Option<BigUint>,
+    /// Hash of the recall chunk, since 2.7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_hash: Option<Base64Url>,
+    /// Hash of the second recall chunk, since 2.7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk2_hash: Option<Base64Url>,
+    /// Hash of the recent block times, since 2.7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time_history_hash: Option<Base64Url>,
+    /// Weave offset of the second recall chunk, since 2.7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recall_byte2: Option<BigUint>,
+}
+
+impl Tag {
+    /// approximate heap size in bytes
+    pub fn size(&self) -> usize {
+        self.name.len() + self.value.len()
+    }
+
+    /// utf-8 decoded name, if valid
+    pub fn decoded_name(&self) -> Option<String> {
+        String::from_utf8(self.name.to_vec()).ok()
+    }
+
+    /// utf-8 decoded value, if valid
+    pub fn decoded_value(&self) -> Option<String> {
+        String::from_utf8(self.value.to_vec()).ok()
+    }
+}
+
+impl Block {
+    /// latest fork of `network` active at the height of this block
+    pub fn fork(&self, network: &Network) -> Option<Fork> {
+        network.fork_at(self.height)
+    }
+}
+
+impl FirehoseBlock {
+    /// latest fork of `network` active at the height of this block
+    ///
+    /// ```rust
+    /// use thegarii::{network::{Fork, Network}, types::{Block, FirehoseBlock}};
+    ///
+    /// let block = FirehoseBlock::from(
+    ///     serde_json::from_str::<Block>(include_str!("../res/block_height_269512.json")).unwrap(),
+    /// );
+    /// assert_eq!(block.fork(&Network::mainnet()), Some(Fork::V1_8));
+    /// assert_eq!(block.fork(&Network::local()), Some(Fork::V2_9));
+    /// ```
+    pub fn fork(&self, network: &Network) -> Option<Fork> {
+        network.fork_at(self.height)
+    }
+
+    /// approximate heap size in bytes, including transactions
+    pub fn size(&self) -> usize {
+        self.indep_hash.len()
+            + self.nonce.len()
+            + self.previous_block.len()
+            + self.hash.len()
+            + self.tx_root.as_ref().map_or(0, |root| root.len())
+            + self.txs.iter().map(Transaction::size).sum::<usize>()
+            + self.wallet_list.len()
+            + self.reward_addr.len()
+            + self.tags.iter().map(Tag::size).sum::<usize>()
+            + self.hash_list_merkle.as_ref().map_or(0, |root| root.len())
+            + self.poa.as_ref().map_or(0, Poa::size)
+            + [
+                &self.hash_preimage,
+                &self.previous_solution_hash,
+                &self.signature,
+                &self.reward_key,
+                &self.reward_history_hash,
+                &self.chunk_hash,
+                &self.chunk2_hash,
+                &self.block_time_history_hash,
+            ]
+            .iter()
+            .map(|bytes| bytes.as_ref().map_or(0, |bytes| bytes.len()))
+            .sum::<usize>()
+            + self
+                .nonce_limiter_info
+                .as_ref()
+                .map_or(0, NonceLimiterInfo::size)
+            + self.poa2.as_ref().map_or(0, Poa::size)
+            + self
+                .double_signing_proof
+                .as_ref()
+                .map_or(0, DoubleSigningProof::size)
+    }
 }
 
 impl From<Block> for FirehoseBlock {
@@ -166,6 +706,104 @@ impl From<Block> for FirehoseBlock {
             cumulative_diff: block.cumulative_diff,
             hash_list_merkle: block.hash_list_merkle,
             poa: block.poa,
+            filtered: false,
+            usd_to_ar_rate: block.usd_to_ar_rate,
+            scheduled_usd_to_ar_rate: block.scheduled_usd_to_ar_rate,
+            packing_2_5_threshold: block.packing_2_5_threshold,
+            strict_data_split_threshold: block.strict_data_split_threshold,
+            hash_preimage: block.hash_preimage,
+            recall_byte: block.recall_byte,
+            reward: block.reward,
+            previous_solution_hash: block.previous_solution_hash,
+            partition_number: block.partition_number,
+            nonce_limiter_info: block.nonce_limiter_info,
+            poa2: block.poa2,
+            signature: block.signature,
+            reward_key: block.reward_key,
+            price_per_gib_minute: block.price_per_gib_minute,
+            scheduled_price_per_gib_minute: block.scheduled_price_per_gib_minute,
+            reward_history_hash: block.reward_history_hash,
+            debt_supply: block.debt_supply,
+            kryder_plus_rate_multiplier: block.kryder_plus_rate_multiplier,
+            kryder_plus_rate_multiplier_latch: block.kryder_plus_rate_multiplier_latch,
+            denomination: block.denomination,
+            redenomination_height: block.redenomination_height,
+            double_signing_proof: block.double_signing_proof,
+            previous_cumulative_diff: block.previous_cumulative_diff,
+            merkle_rebase_support_threshold: block.merkle_rebase_support_threshold,
+            chunk_hash: block.chunk_hash,
+            chunk2_hash: block.chunk2_hash,
+            block_time_history_hash: block.block_time_history_hash,
+            recall_byte2: block.recall_byte2,
+        }
+    }
+}
+
+/// the block as returned by `/block/height/{height}`, `tx_tree` is not kept
+/// in firehose blocks and always empty
+///
+/// ```rust
+/// use thegarii::types::{Block, FirehoseBlock, Transaction};
+///
+/// let block: Block = serde_json::from_str(include_str!("../res/block_height_422250.json")).unwrap();
+/// let mut tx: Transaction = serde_json::from_str(include_str!("../res/tx.json")).unwrap();
+/// tx.id = block.txs[0].clone();
+///
+/// let mut firehose_block: FirehoseBlock = block.clone().into();
+/// firehose_block.txs = vec![tx];
+/// assert_eq!(Block::from(&firehose_block), block);
+/// ```
+impl From<&FirehoseBlock> for Block {
+    fn from(block: &FirehoseBlock) -> Self {
+        Block {
+            nonce: block.nonce.clone(),
+            previous_block: block.previous_block.clone(),
+            timestamp: block.timestamp,
+            last_retarget: block.last_retarget,
+            diff: block.diff,
+            height: block.height,
+            hash: block.hash.clone(),
+            indep_hash: block.indep_hash.clone(),
+            txs: block.txs.iter().map(|tx| tx.id.clone()).collect(),
+            wallet_list: block.wallet_list.clone(),
+            reward_addr: block.reward_addr.clone(),
+            tags: block.tags.clone(),
+            reward_pool: block.reward_pool,
+            weave_size: block.weave_size,
+            block_size: block.block_size,
+            cumulative_diff: block.cumulative_diff,
+            hash_list_merkle: block.hash_list_merkle.clone(),
+            tx_root: block.tx_root.clone(),
+            tx_tree: Some(vec![]),
+            poa: block.poa.clone(),
+            usd_to_ar_rate: block.usd_to_ar_rate.clone(),
+            scheduled_usd_to_ar_rate: block.scheduled_usd_to_ar_rate.clone(),
+            packing_2_5_threshold: block.packing_2_5_threshold,
+            strict_data_split_threshold: block.strict_data_split_threshold,
+            hash_preimage: block.hash_preimage.clone(),
+            recall_byte: block.recall_byte,
+            reward: block.reward,
+            previous_solution_hash: block.previous_solution_hash.clone(),
+            partition_number: block.partition_number,
+            nonce_limiter_info: block.nonce_limiter_info.clone(),
+            poa2: block.poa2.clone(),
+            signature: block.signature.clone(),
+            reward_key: block.reward_key.clone(),
+            price_per_gib_minute: block.price_per_gib_minute,
+            scheduled_price_per_gib_minute: block.scheduled_price_per_gib_minute,
+            reward_history_hash: block.reward_history_hash.clone(),
+            debt_supply: block.debt_supply,
+            kryder_plus_rate_multiplier: block.kryder_plus_rate_multiplier,
+            kryder_plus_rate_multiplier_latch: block.kryder_plus_rate_multiplier_latch,
+            denomination: block.denomination,
+            redenomination_height: block.redenomination_height,
+            double_signing_proof: block.double_signing_proof.clone(),
+            previous_cumulative_diff: block.previous_cumulative_diff,
+            merkle_rebase_support_threshold: block.merkle_rebase_support_threshold,
+            chunk_hash: block.chunk_hash.clone(),
+            chunk2_hash: block.chunk2_hash.clone(),
+            block_time_history_hash: block.block_time_history_hash.clone(),
+            recall_byte2: block.recall_byte2,
         }
     }
 }
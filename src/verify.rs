@@ -0,0 +1,541 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! verification of blocks and transactions served by untrusted gateways
+//!
+//! a transaction is authentic if `signature` is a RSA-PSS signature of its
+//! signed fields by `owner` and `id` is the SHA-256 of `signature`. format 1
+//! transactions sign the concatenation of their fields, format 2 ones sign
+//! the deep hash of them.
+//!
+//! since the 2.0 fork, blocks commit to their transactions with `tx_root`,
+//! the root of the Merkle tree of their data roots and sizes.
+use crate::{
+    metrics,
+    network::{Fork, Network},
+    types::{Base64Url, Block, DataStatus, FirehoseBlock, SignatureStatus, Transaction},
+    Error, Result,
+};
+use rsa::{pss::Pss, BigUint, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384};
+use std::str::FromStr;
+
+/// public exponent of the keys of arweave wallets
+const PUBLIC_EXPONENT: u32 = 65537;
+/// bytes of a chunk of transaction data
+const DATA_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// what to do with transactions failing verification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyPolicy {
+    /// fail the block, it is fetched again by the next poll
+    Reject,
+    /// log invalid transactions and emit them
+    Warn,
+    /// set the signature status of every transaction of the block
+    Annotate,
+}
+
+impl FromStr for VerifyPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "warn" => Ok(Self::Warn),
+            "annotate" => Ok(Self::Annotate),
+            _ => Err(anyhow::anyhow!(
+                "invalid verify policy {}, expect reject, warn or annotate",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// what to do with blocks whose transactions do not hash to their `tx_root`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxRootPolicy {
+    /// fetch the transactions again, fail the block once rounds are exhausted
+    Fail,
+    /// log the mismatch and emit the block
+    Warn,
+    /// do not compute `tx_root`
+    Off,
+}
+
+impl FromStr for TxRootPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "warn" => Ok(Self::Warn),
+            "off" => Ok(Self::Off),
+            _ => Err(
+                anyhow::anyhow!("invalid tx root policy {}, expect fail, warn or off", s).into(),
+            ),
+        }
+    }
+}
+
+/// value hashed by `deep_hash`
+pub enum DeepHashItem<'a> {
+    Blob(&'a [u8]),
+    List(Vec<DeepHashItem<'a>>),
+}
+
+/// arweave deep hash of `item`
+///
+/// ```rust
+/// use thegarii::verify::{deep_hash, DeepHashItem};
+///
+/// let blob = deep_hash(&DeepHashItem::Blob(b"thegarii"));
+/// let list = deep_hash(&DeepHashItem::List(vec![DeepHashItem::Blob(b"thegarii")]));
+/// assert_ne!(blob, list);
+/// ```
+pub fn deep_hash(item: &DeepHashItem) -> [u8; 48] {
+    match item {
+        DeepHashItem::Blob(blob) => {
+            let tag = Sha384::digest(format!("blob{}", blob.len()));
+            Sha384::new()
+                .chain_update(tag)
+                .chain_update(Sha384::digest(blob))
+                .finalize()
+                .into()
+        }
+        DeepHashItem::List(items) => items.iter().fold(
+            Sha384::digest(format!("list{}", items.len())).into(),
+            |acc: [u8; 48], item| {
+                Sha384::new()
+                    .chain_update(acc)
+                    .chain_update(deep_hash(item))
+                    .finalize()
+                    .into()
+            },
+        ),
+    }
+}
+
+/// message signed by the owner of `tx`
+pub fn signature_data(tx: &Transaction) -> Vec<u8> {
+    let quantity = tx.quantity.to_string();
+    let reward = tx.reward.to_string();
+
+    match tx.format {
+        Some(2) => {
+            let format = b"2";
+            let data_size = tx.data_size.to_string();
+            let tags = tx
+                .tags
+                .iter()
+                .map(|tag| {
+                    DeepHashItem::List(vec![
+                        DeepHashItem::Blob(&tag.name),
+                        DeepHashItem::Blob(&tag.value),
+                    ])
+                })
+                .collect();
+
+            deep_hash(&DeepHashItem::List(vec![
+                DeepHashItem::Blob(format),
+                DeepHashItem::Blob(&tx.owner),
+                DeepHashItem::Blob(&tx.target),
+                DeepHashItem::Blob(quantity.as_bytes()),
+                DeepHashItem::Blob(reward.as_bytes()),
+                DeepHashItem::Blob(&tx.last_tx),
+                DeepHashItem::List(tags),
+                DeepHashItem::Blob(data_size.as_bytes()),
+                DeepHashItem::Blob(&tx.data_root),
+            ]))
+            .to_vec()
+        }
+        _ => [
+            &tx.owner[..],
+            &tx.target,
+            &tx.data,
+            quantity.as_bytes(),
+            reward.as_bytes(),
+            &tx.last_tx,
+        ]
+        .into_iter()
+        .chain(tx.tags.iter().flat_map(|tag| [&tag.name[..], &tag.value]))
+        .flatten()
+        .copied()
+        .collect(),
+    }
+}
+
+/// check `tx` is signed by `owner` and identified by its signature, the
+/// status is `Valid` once both are checked
+///
+/// transactions with an empty `owner` carry an ECDSA signature whose key is
+/// not recovered, only their `id` is checked and they stay `Unverified`.
+///
+/// ```rust
+/// use sha2::{Digest, Sha256};
+/// use thegarii::{types::{SignatureStatus, Transaction}, verify};
+///
+/// let tx: Transaction = serde_json::from_str(include_str!("../res/tx.json")).unwrap();
+/// assert_eq!(verify::verify_tx(&tx).unwrap(), SignatureStatus::Valid);
+///
+/// let mut tx: Transaction = serde_json::from_str(include_str!("../res/tx_format_2.json")).unwrap();
+/// assert_eq!(verify::verify_tx(&tx).unwrap(), SignatureStatus::Valid);
+///
+/// // anyone can drop the owner and pick a signature hashing to the id
+/// let mut forged = tx.clone();
+/// forged.owner = Default::default();
+/// forged.signature = b"forged".to_vec().into();
+/// forged.id = Sha256::digest(b"forged").to_vec().into();
+/// assert_eq!(verify::verify_tx(&forged).unwrap(), SignatureStatus::Unverified);
+///
+/// tx.reward = 1.into();
+/// assert_eq!(
+///     verify::verify_tx(&tx).unwrap_err().to_string(),
+///     format!("invalid tx {}: signature does not match owner", tx.id),
+/// );
+/// ```
+pub fn verify_tx(tx: &Transaction) -> Result<SignatureStatus> {
+    let invalid = |reason: &str| Error::InvalidTx {
+        id: tx.id.to_string(),
+        reason: reason.to_string(),
+    };
+
+    if Sha256::digest(&tx.signature[..])[..] != tx.id[..] {
+        return Err(invalid("id is not the hash of signature"));
+    }
+    if tx.owner.is_empty() {
+        return Ok(SignatureStatus::Unverified);
+    }
+
+    let key = RsaPublicKey::new(
+        BigUint::from_bytes_be(&tx.owner),
+        BigUint::from(PUBLIC_EXPONENT),
+    )
+    .map_err(|_| invalid("owner is not a RSA public key"))?;
+    let hashed = Sha256::digest(signature_data(tx));
+
+    // salts are as long as the hash, except for some early wallets
+    key.verify(Pss::new::<Sha256>(), &hashed, &tx.signature)
+        .or_else(|_| key.verify(Pss::new_with_salt::<Sha256>(0), &hashed, &tx.signature))
+        .map_err(|_| invalid("signature does not match owner"))?;
+    Ok(SignatureStatus::Valid)
+}
+
+/// verify the transactions of `block` wrt `policy`
+///
+/// ```rust
+/// use thegarii::{
+///     types::{Base64Url, Block, DataStatus, FirehoseBlock, SignatureStatus, Transaction},
+///     verify::{self, VerifyPolicy},
+/// };
+///
+/// let block: Block = serde_json::from_str(include_str!("../res/block_height_100.json")).unwrap();
+/// let mut block = FirehoseBlock::from(block);
+/// let tx: Transaction = serde_json::from_str(include_str!("../res/tx_format_2.json")).unwrap();
+/// let mut forged = tx.clone();
+/// forged.target = forged.owner.clone();
+/// block.txs = vec![tx.clone(), forged];
+///
+/// assert!(verify::verify_block(block.clone(), VerifyPolicy::Reject).is_err());
+///
+/// let annotated = verify::verify_block(block.clone(), VerifyPolicy::Annotate).unwrap();
+/// assert_eq!(annotated.txs[0].signature_status, SignatureStatus::Valid);
+/// assert_eq!(annotated.txs[1].signature_status, SignatureStatus::Invalid);
+///
+/// // a tx without owner is never counted as valid
+/// let mut unsigned = tx;
+/// unsigned.owner = Default::default();
+/// block.txs = vec![unsigned];
+/// let annotated = verify::verify_block(block, VerifyPolicy::Annotate).unwrap();
+/// assert_eq!(annotated.txs[0].signature_status, SignatureStatus::Unverified);
+/// ```
+pub fn verify_block(mut block: FirehoseBlock, policy: VerifyPolicy) -> Result<FirehoseBlock> {
+    for tx in block.txs.iter_mut() {
+        let status = match verify_tx(tx) {
+            Ok(status) => status,
+            Err(e) => {
+                metrics::TXS_INVALID.inc();
+                match policy {
+                    VerifyPolicy::Reject => return Err(e),
+                    VerifyPolicy::Warn => tracing::warn!(height = block.height, "{}", e),
+                    VerifyPolicy::Annotate => {}
+                }
+                SignatureStatus::Invalid
+            }
+        };
+
+        if policy == VerifyPolicy::Annotate {
+            tx.signature_status = status;
+        }
+    }
+
+    Ok(block)
+}
+
+/// SHA-256 of the concatenation of `parts`
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    parts
+        .iter()
+        .fold(Sha256::new(), |hasher, part| hasher.chain_update(part))
+        .finalize()
+        .into()
+}
+
+/// node of an arweave Merkle tree, `max` is the highest offset below it
+struct Node {
+    id: [u8; 32],
+    max: u64,
+}
+
+/// 256 bits big endian offset
+fn note(offset: u64) -> [u8; 32] {
+    let mut note = [0; 32];
+    note[24..].copy_from_slice(&offset.to_be_bytes());
+    note
+}
+
+/// root of the arweave Merkle tree of `leaves`, each leaf is some data and
+/// the end offset of what it stands for, empty without leaves
+///
+/// nodes are paired level by level, the last node of a level with an odd
+/// length is carried to the next one.
+pub fn merkle_root(leaves: impl IntoIterator<Item = (Vec<u8>, u64)>) -> Vec<u8> {
+    let mut nodes = leaves
+        .into_iter()
+        .map(|(data, offset)| Node {
+            id: sha256(&[&sha256(&[&data]), &sha256(&[&note(offset)])]),
+            max: offset,
+        })
+        .collect::<Vec<_>>();
+
+    while nodes.len() > 1 {
+        let mut level = nodes.into_iter();
+        nodes = std::iter::from_fn(|| {
+            let left = level.next()?;
+            Some(match level.next() {
+                Some(right) => Node {
+                    id: sha256(&[
+                        &sha256(&[&left.id]),
+                        &sha256(&[&right.id]),
+                        &sha256(&[&note(left.max)]),
+                    ]),
+                    max: right.max,
+                },
+                None => left,
+            })
+        })
+        .collect();
+    }
+
+    nodes.pop().map(|root| root.id.to_vec()).unwrap_or_default()
+}
+
+/// leaf data and end offset proven under `root` by `path` for `offset`, as
+/// the `tx_path` and `data_path` of a proof of access, `None` if the path
+/// does not hash to `root`
+///
+/// ```rust
+/// use thegarii::verify;
+///
+/// let root = verify::merkle_root([(vec![7; 32], 4)]);
+/// let mut path = [7; 64];
+/// path[32..].copy_from_slice(&[0; 32]);
+/// path[63] = 4;
+/// assert_eq!(verify::validate_path(&root, 0, &path), Some((vec![7; 32], 4)));
+/// assert_eq!(verify::validate_path(&root, 0, &path[..63]), None);
+/// ```
+pub fn validate_path(root: &[u8], offset: u64, path: &[u8]) -> Option<(Vec<u8>, u64)> {
+    let offset_of = |note: &[u8]| match note.split_at(24) {
+        (high, low) if high.iter().all(|b| *b == 0) => {
+            Some(u64::from_be_bytes(low.try_into().ok()?))
+        }
+        _ => None,
+    };
+
+    let mut id = root;
+    let mut path = path;
+    while path.len() > 64 {
+        let (left, rest) = path.split_at_checked(32)?;
+        let (right, rest) = rest.split_at_checked(32)?;
+        let (note, rest) = rest.split_at_checked(32)?;
+        if sha256(&[&sha256(&[left]), &sha256(&[right]), &sha256(&[note])]) != id {
+            return None;
+        }
+
+        id = if offset < offset_of(note)? {
+            left
+        } else {
+            right
+        };
+        path = rest;
+    }
+
+    let (data, note) = path.split_at_checked(32)?;
+    if note.len() != 32 || sha256(&[&sha256(&[data]), &sha256(&[note])]) != id {
+        return None;
+    }
+
+    Some((data.to_vec(), offset_of(note)?))
+}
+
+/// data root of `tx`, computed from the chunks of `data` for format 1
+/// transactions
+fn data_root(tx: &Transaction) -> Vec<u8> {
+    if tx.format == Some(2) {
+        return tx.data_root.to_vec();
+    }
+
+    // a trailing empty chunk is kept as arweave nodes do
+    let mut offset = 0;
+    let mut chunks = tx.data.chunks(DATA_CHUNK_SIZE as usize).collect::<Vec<_>>();
+    if tx.data.len() as u64 % DATA_CHUNK_SIZE == 0 {
+        chunks.push(&[]);
+    }
+    merkle_root(chunks.into_iter().map(|chunk| {
+        offset += chunk.len() as u64;
+        (sha256(&[chunk]).to_vec(), offset)
+    }))
+}
+
+/// `tx_root` of `txs` in a block at `height` on `network`
+///
+/// ```rust
+/// use thegarii::{network::Network, types::Transaction, verify};
+///
+/// let mainnet = Network::mainnet();
+/// let tx: Transaction = serde_json::from_str(include_str!("../res/tx_format_2.json")).unwrap();
+/// assert!(verify::tx_root(&[], 422250, &mainnet).is_empty());
+/// assert_eq!(verify::tx_root(&[tx.clone()], 422250, &mainnet).len(), 32);
+///
+/// // data is padded to whole chunks since 2.5
+/// assert_ne!(
+///     verify::tx_root(&[tx.clone()], 422250, &mainnet),
+///     verify::tx_root(&[tx.clone()], 812970, &mainnet)
+/// );
+/// // which is active from the genesis of local chains
+/// assert_eq!(
+///     verify::tx_root(&[tx.clone()], 422250, &Network::local()),
+///     verify::tx_root(&[tx], 812970, &mainnet)
+/// );
+/// ```
+pub fn tx_root(txs: &[Transaction], height: u64, network: &Network) -> Vec<u8> {
+    let mut leaves = vec![];
+    let mut offset: u64 = 0;
+    for tx in txs {
+        let data_size = tx.data_size.to_u64().unwrap_or(u64::MAX);
+        offset = offset.saturating_add(data_size);
+        leaves.push((data_root(tx), offset));
+
+        // padding up to the next chunk, with an empty data root
+        if network.activated(Fork::V2_5, height) && data_size % DATA_CHUNK_SIZE != 0 {
+            offset = offset.saturating_add(DATA_CHUNK_SIZE - data_size % DATA_CHUNK_SIZE);
+            leaves.push((vec![], offset));
+        }
+    }
+
+    merkle_root(leaves)
+}
+
+/// check `txs` are the transactions `block` commits to, their ids are the
+/// ones of the block and since the 2.0 fork of `network` they hash to its
+/// `tx_root`
+///
+/// ```rust
+/// use thegarii::{network::Network, types::{Block, Transaction}, verify};
+///
+/// let mainnet = Network::mainnet();
+/// let mut block: Block = serde_json::from_str(include_str!("../res/block_height_422250.json")).unwrap();
+/// let tx: Transaction = serde_json::from_str(include_str!("../res/tx_format_2.json")).unwrap();
+/// block.txs = vec![tx.id.clone()];
+/// block.tx_root = Some(verify::tx_root(&[tx.clone()], block.height, &mainnet).into());
+/// assert!(verify::verify_txs(&block, &[tx.clone()], &mainnet).is_ok());
+///
+/// let mut forged = tx;
+/// forged.data_size = 1.into();
+/// assert!(verify::verify_tx_ids(&block, &[forged.clone()]).is_ok());
+/// assert!(verify::verify_txs(&block, &[forged], &mainnet).is_err());
+/// ```
+pub fn verify_txs(block: &Block, txs: &[Transaction], network: &Network) -> Result<()> {
+    verify_tx_ids(block, txs)?;
+    verify_tx_root(block, txs, network)
+}
+
+/// check the ids of `txs` are the `txs` of `block`, in order
+pub fn verify_tx_ids(block: &Block, txs: &[Transaction]) -> Result<()> {
+    let mismatch = |reason: String| Error::TxsMismatch {
+        height: block.height,
+        reason,
+    };
+
+    if txs.len() != block.txs.len() {
+        return Err(mismatch(format!(
+            "{} txs, expected {}",
+            txs.len(),
+            block.txs.len()
+        )));
+    }
+    if let Some((idx, tx)) = txs
+        .iter()
+        .enumerate()
+        .find(|(idx, tx)| tx.id != block.txs[*idx])
+    {
+        return Err(mismatch(format!(
+            "tx {} is {}, expected {}",
+            idx, tx.id, block.txs[idx]
+        )));
+    }
+
+    Ok(())
+}
+
+/// if the data root of `tx` is known, format 1 transactions need their whole
+/// payload, which has not been fetched when skipped
+fn has_data_root(tx: &Transaction) -> bool {
+    tx.format == Some(2)
+        || (tx.data_status != DataStatus::Skipped
+            && tx.data_size.to_u64() == Some(tx.data.len() as u64))
+}
+
+/// check `txs` hash to the `tx_root` of `block`, since the 2.0 fork of
+/// `network`
+///
+/// the check is skipped when the payload of a format 1 transaction is
+/// missing, its data root can not be recomputed then.
+///
+/// ```rust
+/// use thegarii::{network::Network, types::{Block, DataStatus, Transaction}, verify};
+///
+/// let mainnet = Network::mainnet();
+/// let mut block: Block = serde_json::from_str(include_str!("../res/block_height_422250.json")).unwrap();
+/// let tx: Transaction = serde_json::from_str(include_str!("../res/tx.json")).unwrap();
+/// block.txs = vec![tx.id.clone()];
+/// assert!(verify::verify_tx_root(&block, &[tx.clone()], &mainnet).is_err());
+///
+/// let mut skipped = tx;
+/// skipped.data = Default::default();
+/// skipped.data_status = DataStatus::Skipped;
+/// assert!(verify::verify_tx_root(&block, &[skipped], &mainnet).is_ok());
+/// ```
+pub fn verify_tx_root(block: &Block, txs: &[Transaction], network: &Network) -> Result<()> {
+    if !txs.iter().all(has_data_root) {
+        tracing::debug!(
+            height = block.height,
+            "skipping the tx_root check, the data of a format 1 tx is missing"
+        );
+        return Ok(());
+    }
+
+    if network.activated(Fork::V2_0, block.height) {
+        let expected = block.tx_root.clone().unwrap_or_default();
+        let actual = Base64Url::from(tx_root(txs, block.height, network));
+        if actual != expected {
+            return Err(Error::TxsMismatch {
+                height: block.height,
+                reason: format!("txs hash to {}, expected tx_root {}", actual, expected),
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,316 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! the poller and the client against a simulated arweave node injecting
+//! faults, the emitted FIRE stream must be continuous and match the
+//! canonical chain of the simulator
+mod cli;
+mod simulator;
+
+use cli::{check_stream, flaky, poll, poll_with, CONFIRMS};
+use futures::StreamExt;
+use simulator::{Faults, Reorg, Simulator};
+use std::time::Duration;
+use thegarii::{
+    client::{BlockEvent, Follow, TxFailurePolicy},
+    network::Network,
+    types::Base64Url,
+    Client,
+};
+
+/// time between two blocks of the simulator
+const BLOCK_TIME: Duration = Duration::from_millis(50);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn emits_canonical_stream() {
+    let simulator = Simulator::start(20, BLOCK_TIME, Faults::default()).await;
+    let stdout = poll(&simulator, "canonical", 30).await;
+    check_stream(&simulator, &stdout, 30);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn emits_continuous_stream_from_flaky_gateway() {
+    // start close to the head so the poller keeps fetching it
+    let simulator = Simulator::start(CONFIRMS + 2, BLOCK_TIME, flaky()).await;
+    let stdout = poll(&simulator, "flaky", 40).await;
+    check_stream(&simulator, &stdout, 40);
+
+    let injected = simulator.injected();
+    assert!(
+        injected.throttled > 0 && injected.server_errors > 0 && injected.truncated > 0,
+        "{:?}",
+        injected
+    );
+    assert!(injected.missing_txs > 0, "{:?}", injected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn emits_continuous_stream_from_lagging_gateway() {
+    let faults = Faults {
+        stale_head: 0.5,
+        stale_depth: 10,
+        ..Default::default()
+    };
+    let simulator = Simulator::start(CONFIRMS + 2, BLOCK_TIME, faults).await;
+    let stdout = poll(&simulator, "lagging", 40).await;
+    check_stream(&simulator, &stdout, 40);
+    assert!(simulator.injected().stale_heads > 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ignores_reorgs_within_confirmations() {
+    let faults = Faults {
+        reorg: Some(Reorg {
+            every: 3,
+            depth: CONFIRMS - 1,
+        }),
+        ..flaky()
+    };
+    let simulator = Simulator::start(10, BLOCK_TIME, faults).await;
+    let stdout = poll(&simulator, "reorgs", 40).await;
+    check_stream(&simulator, &stdout, 40);
+    assert!(simulator.injected().reorgs > 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keeps_buffered_blocks_within_memory_budget() {
+    // a couple of blocks of the simulator, less than the 4 in flight
+    const BUDGET: u64 = 1024;
+
+    let simulator = Simulator::start(20, BLOCK_TIME, Faults::default()).await;
+    let output = poll_with(
+        &simulator,
+        "budget",
+        30,
+        &[
+            "--memory-budget",
+            &BUDGET.to_string(),
+            "--log-format",
+            "json",
+        ],
+        "thegarii=debug",
+    )
+    .await;
+    check_stream(&simulator, &String::from_utf8(output.stdout).unwrap(), 30);
+
+    // bytes buffered once each block is fetched, reservations included
+    let (mut peak, mut largest) = (0, 0);
+    for line in String::from_utf8(output.stderr).unwrap().lines() {
+        let Ok(log) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if log["fields"]["message"] == "fetched block" {
+            peak = peak.max(log["fields"]["buffered"].as_u64().unwrap());
+            largest = largest.max(log["fields"]["bytes"].as_u64().unwrap());
+        }
+    }
+    assert!(
+        largest > 0 && largest < BUDGET,
+        "blocks of {} bytes",
+        largest
+    );
+    assert!(
+        peak <= BUDGET + largest,
+        "{} bytes buffered, budget of {}",
+        peak,
+        BUDGET
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_fails_over_to_healthy_endpoint() {
+    let broken = Simulator::start(
+        30,
+        Duration::from_secs(3600),
+        Faults {
+            server_error: 0.9,
+            ..Default::default()
+        },
+    )
+    .await;
+    let healthy = Simulator::start(30, Duration::from_secs(3600), flaky()).await;
+
+    let client = Client::new(
+        vec![broken.endpoint(), healthy.endpoint()],
+        Duration::from_secs(5),
+        10,
+        Network::mainnet(),
+    )
+    .unwrap()
+    .with_retry_delay(Duration::from_millis(20))
+    .with_tx_rounds(5, TxFailurePolicy::Fail);
+
+    for block in client.poll(0..=30, 4).await.unwrap() {
+        assert_eq!(
+            block.indep_hash.to_string(),
+            healthy.indep_hash(block.height)
+        );
+        assert_eq!(
+            block
+                .txs
+                .iter()
+                .map(|tx| tx.id.to_string())
+                .collect::<Vec<_>>(),
+            healthy.tx_ids(block.height)
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_refetches_forged_txs() {
+    let faults = Faults {
+        forged_tx: 0.1,
+        ..Default::default()
+    };
+    let simulator = Simulator::start(30, Duration::from_secs(3600), faults).await;
+    let client = client(&simulator);
+
+    // a few passes, until forged txs have been served
+    for _ in 0..5 {
+        for block in client.poll(0..=30, 4).await.unwrap() {
+            assert_eq!(
+                block
+                    .txs
+                    .iter()
+                    .map(|tx| tx.id.to_string())
+                    .collect::<Vec<_>>(),
+                simulator.tx_ids(block.height)
+            );
+        }
+        if simulator.injected().forged_txs > 0 {
+            break;
+        }
+    }
+    assert!(simulator.injected().forged_txs > 0);
+}
+
+/// client of a single simulator with short retry delays
+fn client(simulator: &Simulator) -> Client {
+    Client::new(
+        vec![simulator.endpoint()],
+        Duration::from_secs(5),
+        10,
+        Network::mainnet(),
+    )
+    .unwrap()
+    .with_retry_delay(Duration::from_millis(20))
+    .with_tx_rounds(5, TxFailurePolicy::Fail)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streams_blocks_in_order() {
+    let simulator = Simulator::start(30, Duration::from_secs(3600), flaky()).await;
+    let client = client(&simulator);
+
+    let blocks = client
+        .stream_blocks(0..=30, 8)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        blocks.iter().map(|block| block.height).collect::<Vec<_>>(),
+        (0..=30).collect::<Vec<_>>()
+    );
+    for block in blocks {
+        assert_eq!(
+            block.indep_hash.to_string(),
+            simulator.indep_hash(block.height)
+        );
+    }
+}
+
+/// follow the head of a simulator reorging the last `depth` blocks to
+/// block `END`, returns the deepest notified reorg
+async fn follow_reorgs(depth: u64, max_reorg_depth: usize) -> usize {
+    const END: u64 = 40;
+
+    let faults = Faults {
+        latency: Duration::from_millis(10),
+        reorg: Some(Reorg { every: 3, depth }),
+        ..Default::default()
+    };
+    let simulator = Simulator::start(10, BLOCK_TIME, faults).await;
+    // txs of orphaned blocks never show up, fail them fast
+    let client = Client::new(
+        vec![simulator.endpoint()],
+        Duration::from_secs(5),
+        3,
+        Network::mainnet(),
+    )
+    .unwrap()
+    .with_retry_delay(Duration::from_millis(20));
+    let options = Follow {
+        concurrency: 4,
+        confirms: 0,
+        interval: Duration::from_millis(10),
+        max_reorg_depth,
+    };
+
+    // apply the events on a local chain
+    let mut chain: Vec<(u64, Base64Url)> = vec![];
+    let (mut undos, mut deepest) = (0, 0);
+    let mut events = client.follow_blocks(0, options);
+    let follow = async {
+        while let Some(event) = events.next().await {
+            // blocks replaced during their fetch fail and are refetched
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    assert!(!e.to_string().contains("deeper"), "{}", e);
+                    continue;
+                }
+            };
+            match event {
+                BlockEvent::New(block) => {
+                    let parent = chain.last().map(|(_, hash)| hash.clone());
+                    assert_eq!(block.height, chain.len() as u64, "stream is not continuous");
+                    assert_eq!(
+                        block.previous_block,
+                        parent.unwrap_or_default(),
+                        "parent of block {} mismatch",
+                        block.height
+                    );
+                    chain.push((block.height, block.indep_hash.clone()));
+                    undos = 0;
+                    if block.height == END {
+                        break;
+                    }
+                }
+                BlockEvent::Undo { height, indep_hash } => {
+                    assert_eq!(chain.pop(), Some((height, indep_hash)));
+                    undos += 1;
+                    deepest = deepest.max(undos);
+                }
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(120), follow)
+        .await
+        .expect("follower timed out");
+    assert_eq!(chain.len() as u64, END + 1, "stream ended early");
+
+    // blocks are final once the head is `depth` blocks above them
+    for (height, indep_hash) in &chain[..=(END - depth) as usize] {
+        assert_eq!(
+            indep_hash.to_string(),
+            simulator.indep_hash(*height),
+            "block {} is not canonical",
+            height
+        );
+    }
+    assert!(simulator.injected().reorgs > 0);
+    deepest
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follows_head_through_reorgs() {
+    assert!(follow_reorgs(2, 10).await > 0, "no reorg has been notified");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follows_reorgs_as_deep_as_tracked() {
+    // the new head is part of the reorg, emitted blocks are reorged up to
+    // `depth - 1` deep
+    assert_eq!(follow_reorgs(4, 3).await, 3);
+}
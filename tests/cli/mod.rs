@@ -0,0 +1,179 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! thegarii commands run against simulated arweave nodes
+#![allow(dead_code)]
+use crate::simulator::{Faults, Simulator};
+use base64::{engine::general_purpose, Engine as _};
+use prost::Message;
+use std::{
+    path::PathBuf,
+    process::Stdio,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use thegarii::pb;
+use tokio::process::Command;
+
+/// blocks behind the head considered irreversible by the poller
+pub const CONFIRMS: u64 = 6;
+
+/// scratch directories created by this process
+static SCRATCH: AtomicUsize = AtomicUsize::new(0);
+
+/// empty path of a scratch directory for `name`, unique to each call
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "thegarii-{}-{}-{}",
+        name,
+        std::process::id(),
+        SCRATCH.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// faults of a flaky gateway
+pub fn flaky() -> Faults {
+    Faults {
+        latency: Duration::from_millis(20),
+        throttle: 0.1,
+        server_error: 0.1,
+        truncate: 0.1,
+        stale_head: 0.3,
+        stale_depth: 10,
+        missing_tx: 0.2,
+        forged_tx: 0.0,
+        reorg: None,
+    }
+}
+
+/// run the poller from block 0 to `end` and return its stdout
+pub async fn poll(simulator: &Simulator, name: &str, end: u64) -> String {
+    let output = poll_with(simulator, name, end, &[], "warn").await;
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// run the poller from block 0 to `end` with the extra options `args`,
+/// logging at `log`
+pub async fn poll_with(
+    simulator: &Simulator,
+    name: &str,
+    end: u64,
+    args: &[&str],
+    log: &str,
+) -> std::process::Output {
+    let dir = scratch(name);
+
+    let child = Command::new(env!("CARGO_BIN_EXE_thegarii"))
+        .args(["--endpoints", &simulator.endpoint()])
+        .args(["--confirms", &CONFIRMS.to_string()])
+        .args(["--block-time", "50", "--retry", "10", "--retry-delay", "20"])
+        .args(["--tx-rounds", "5", "--batch-blocks", "4"])
+        .args(args)
+        .arg("console")
+        .args(["--data-directory", &dir.join("state").to_string_lossy()])
+        .args(["--start", "0", "--end", &end.to_string()])
+        .env("DB_PATH", dir.join("db"))
+        .env("RUST_LOG", log)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let output = tokio::time::timeout(Duration::from_secs(120), child.wait_with_output())
+        .await
+        .expect("poller timed out")
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        output.status.success(),
+        "poller failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// run thegarii against `endpoints` with the command `args`
+pub async fn run(endpoints: &[String], args: &[&str]) -> std::process::Output {
+    let dir = scratch(args[0]);
+    let output = Command::new(env!("CARGO_BIN_EXE_thegarii"))
+        .args(endpoints.iter().flat_map(|e| ["--endpoints", e]))
+        .args(["--confirms", &CONFIRMS.to_string()])
+        .args(["--retry", "10", "--retry-delay", "20", "--tx-rounds", "5"])
+        .args(args)
+        .env("DB_PATH", &dir)
+        .env("RUST_LOG", "warn")
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(60), output)
+        .await
+        .expect("thegarii timed out")
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    output
+}
+
+/// hex of a base64url string
+pub fn hex(b64: &str) -> String {
+    hex::encode(base64_url::decode(b64).unwrap())
+}
+
+/// assert the FIRE stream covers `0..=end` continuously with canonical blocks
+pub fn check_stream(simulator: &Simulator, stdout: &str, end: u64) {
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some(thegarii::firehose::INIT));
+
+    let mut parent_hash = String::new();
+    let mut expected_height = 0;
+    for line in lines {
+        let fields = line.split(' ').collect::<Vec<_>>();
+        assert_eq!(fields[..2], ["FIRE", "BLOCK"], "unexpected line {}", line);
+
+        let height: u64 = fields[2].parse().unwrap();
+        assert_eq!(height, expected_height, "stream is not continuous");
+        assert_eq!(
+            fields[3],
+            hex(&simulator.indep_hash(height)),
+            "block {} is not canonical",
+            height
+        );
+        assert_eq!(
+            fields[5], parent_hash,
+            "parent of block {} mismatch",
+            height
+        );
+        assert_eq!(fields[6], height.saturating_sub(CONFIRMS).to_string());
+
+        let block = pb::Block::decode(
+            general_purpose::STANDARD
+                .decode(fields[8])
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(block.height, height);
+        assert_eq!(
+            block
+                .txs
+                .iter()
+                .map(|tx| base64_url::encode(&tx.id))
+                .collect::<Vec<_>>(),
+            simulator.tx_ids(height),
+            "txs of block {} mismatch",
+            height
+        );
+
+        parent_hash = fields[3].to_string();
+        expected_height += 1;
+    }
+
+    assert_eq!(
+        expected_height,
+        end + 1,
+        "stream stopped before block {}",
+        end
+    );
+}
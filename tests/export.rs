@@ -0,0 +1,66 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! `thegarii export` of the blocks of a simulated arweave node
+mod cli;
+mod simulator;
+
+use cli::{flaky, run, scratch};
+use simulator::Simulator;
+use std::time::Duration;
+use thegarii::{
+    archive::{self, Compression, Format},
+    types::FirehoseBlock,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exports_and_resumes_ranges() {
+    let simulator = Simulator::start(12, Duration::from_secs(3600), flaky()).await;
+    let dir = scratch("archive");
+    let export = |end: u64| {
+        let (dir, endpoint) = (dir.clone(), simulator.endpoint());
+        async move {
+            let out = dir.to_string_lossy().to_string();
+            let end = end.to_string();
+            let args = ["export", "--start", "0", "--end", &end, "--out", &out];
+            let options = ["--blocks-per-file", "4", "--compression", "zstd"];
+            let output = run(&[endpoint], &[&args[..], &options].concat()).await;
+            assert!(
+                output.status.success(),
+                "export failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    };
+    let ranges = || {
+        archive::files(&dir, Format::Jsonl, Compression::Zstd)
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.first, file.last))
+            .collect::<Vec<_>>()
+    };
+
+    export(9).await;
+    assert_eq!(ranges(), [(0, 3), (4, 7), (8, 9)]);
+
+    // interrupted while writing the last file
+    let last = archive::files(&dir, Format::Jsonl, Compression::Zstd).unwrap()[2].clone();
+    std::fs::rename(&last.path, dir.join("000000000008.partial")).unwrap();
+    export(11).await;
+    assert_eq!(ranges(), [(0, 3), (4, 7), (8, 11)]);
+
+    let mut heights = vec![];
+    for file in archive::files(&dir, Format::Jsonl, Compression::Zstd).unwrap() {
+        let jsonl = zstd::decode_all(std::fs::File::open(&file.path).unwrap()).unwrap();
+        for line in String::from_utf8(jsonl).unwrap().lines() {
+            let block: FirehoseBlock = serde_json::from_str(line).unwrap();
+            assert_eq!(
+                block.indep_hash.to_string(),
+                simulator.indep_hash(block.height)
+            );
+            heights.push(block.height);
+        }
+    }
+    assert_eq!(heights, (0..=11).collect::<Vec<_>>());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,98 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! `thegarii get` against a simulated arweave node
+mod cli;
+mod simulator;
+
+use base64::{engine::general_purpose, Engine as _};
+use cli::{flaky, hex, run, CONFIRMS};
+use prost::Message;
+use simulator::Simulator;
+use std::time::Duration;
+use thegarii::pb;
+
+/// run `thegarii get` with `args` and return its stdout
+async fn get(simulator: &Simulator, args: &[&str]) -> String {
+    let output = run(&[simulator.endpoint()], &[&["get"], args].concat()).await;
+    assert!(
+        output.status.success(),
+        "get failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn gets_blocks_by_height_hash_and_tx() {
+    let simulator = Simulator::start(12, Duration::from_secs(3600), flaky()).await;
+
+    let lines = get(&simulator, &["2..=5", "--format", "base64"]).await;
+    let heights = lines
+        .lines()
+        .map(|line| {
+            pb::Block::decode(general_purpose::STANDARD.decode(line).unwrap().as_slice())
+                .unwrap()
+                .height
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(heights, vec![2, 3, 4, 5]);
+
+    let hash = simulator.indep_hash(7);
+    let json: serde_json::Value =
+        serde_json::from_str(&get(&simulator, &["--hash", &hash, "--format", "arweave"]).await)
+            .unwrap();
+    assert_eq!(json["height"], 7);
+    assert_eq!(json["indep_hash"], hash);
+
+    let id = &simulator.tx_ids(8)[1];
+    let line = get(&simulator, &["--tx", id, "--format", "fire"]).await;
+    let fields = line.trim_end().split(' ').collect::<Vec<_>>();
+    assert_eq!(
+        fields[..4],
+        ["FIRE", "BLOCK", "8", &hex(&simulator.indep_hash(8))]
+    );
+    assert_eq!(fields[6], (8 - CONFIRMS).to_string());
+}
+
+/// `get` leaves the block database to a running console unless `--store`
+#[cfg(feature = "full")]
+#[tokio::test(flavor = "multi_thread")]
+async fn gets_blocks_while_the_database_is_locked() {
+    let simulator = Simulator::start(12, Duration::from_secs(3600), Default::default()).await;
+    let dir = cli::scratch("locked");
+    let storage = thegarii::storage::Storage::open(&dir).unwrap();
+
+    let get = |store: bool| {
+        let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_thegarii"));
+        command
+            .args(["--endpoints", &simulator.endpoint(), "--retry", "0"])
+            .args(["get", "3"])
+            .args(store.then_some("--store"))
+            .env("DB_PATH", &dir)
+            .env("RUST_LOG", "warn")
+            .kill_on_drop(true);
+        async move {
+            tokio::time::timeout(Duration::from_secs(60), command.output())
+                .await
+                .expect("thegarii timed out")
+                .unwrap()
+        }
+    };
+
+    let output = get(false).await;
+    assert!(
+        output.status.success(),
+        "get failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let block: thegarii::types::FirehoseBlock = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(block.height, 3);
+
+    let output = get(true).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("a single process can open it"));
+
+    drop(storage);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,267 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! golden firehose logs of the block format eras
+//!
+//! each fixture in `res/` is rendered with `res/tx.json` as its only
+//! transaction, with a short payload instead of its 1MB one, the FIRE line
+//! and the JSON of the decoded `pb::Block` must match `res/golden/` byte for
+//! byte, rerun with `UPDATE_GOLDEN=1` to regenerate them after an intended
+//! change. the decoded JSON must also carry the values of the fixture, so a
+//! regenerated golden file cannot lock in a misencoded field.
+//!
+//! `res/synthetic_block_2_7.json` is synthetic, it follows the shape of the
+//! blocks of 2.7 nodes with derived hashes and short proofs.
+use prost::Message;
+use serde_json::Value;
+use std::{env, fs, path::Path};
+use thegarii::{
+    firehose::{self, Render},
+    network::Network,
+    pb,
+    types::{Block, FirehoseBlock, Transaction},
+};
+
+/// blocks behind the head considered irreversible
+const CONFIRMS: u64 = 20;
+
+/// firehose block of a fixture with `res/tx.json` as its transaction
+fn firehose_block(json: &str) -> FirehoseBlock {
+    let block: Block = serde_json::from_str(json).unwrap();
+    let mut tx: Transaction = serde_json::from_str(include_str!("../res/tx.json")).unwrap();
+    tx.data = b"thegarii".to_vec().into();
+    tx.data_size = 8.into();
+
+    let mut block: FirehoseBlock = block.into();
+    block.txs = vec![tx];
+    block
+}
+
+/// decode the payload of a FIRE line
+fn decode(line: &str) -> pb::Block {
+    firehose::parse_block_line(line).unwrap()
+}
+
+/// compare `actual` with the golden file `name`
+fn check(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("res/golden")
+        .join(name);
+    if env::var("UPDATE_GOLDEN").is_ok() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing {:?}, rerun with UPDATE_GOLDEN=1", path));
+    if let Some(offset) = expected
+        .bytes()
+        .zip(actual.bytes())
+        .position(|(e, a)| e != a)
+        .or((expected.len() != actual.len()).then(|| expected.len().min(actual.len())))
+    {
+        panic!(
+            "{:?} differs at byte {}: expected {:?}, got {:?}",
+            path,
+            offset,
+            &expected[offset..expected.len().min(offset + 64)],
+            &actual[offset..actual.len().min(offset + 64)],
+        );
+    }
+}
+
+/// check the scalar fields of the `fixture` JSON are the ones of the
+/// `decoded` JSON, so golden files cannot lock in misencoded fields, arrays
+/// and fields missing from the payload are skipped
+fn same_as_fixture(fixture: &Value, decoded: &Value, path: &str) {
+    match (fixture, decoded) {
+        (Value::Object(fixture), Value::Object(decoded)) => {
+            for (key, value) in fixture {
+                if let Some(decoded) = decoded.get(key) {
+                    same_as_fixture(value, decoded, &format!("{}.{}", path, key));
+                }
+            }
+        }
+        // `{}` stands for a missing field
+        (Value::Array(_), _) | (Value::Object(_), Value::Null) => {}
+        // big integers are decimal strings once decoded
+        (Value::Number(n), Value::String(s)) => {
+            assert_eq!(&n.to_string(), s, "{} differs from the fixture", path)
+        }
+        _ => assert_eq!(fixture, decoded, "{} differs from the fixture", path),
+    }
+}
+
+/// check the FIRE line and the decoded JSON of a fixture against
+/// `res/golden/{name}.*`
+fn golden(name: &str, json: &str) {
+    let line =
+        firehose::block_line(firehose_block(json), &Network::mainnet(), CONFIRMS, false).unwrap();
+    check(&format!("{}.fire", name), &format!("{}\n", line));
+
+    let decoded = decode(&line);
+    let text_roots = Render {
+        text_roots: true,
+        ..Default::default()
+    };
+    same_as_fixture(
+        &serde_json::from_str(json).unwrap(),
+        &text_roots.block_json(&decoded),
+        "",
+    );
+    let rendered = firehose::block_json(&decoded);
+    let pretty = serde_json::to_string_pretty(&rendered).unwrap();
+    check(&format!("{}.json", name), &format!("{}\n", pretty));
+
+    round_trip(json, decoded);
+}
+
+/// convert a decoded payload back to the firehose block and the Arweave
+/// JSON it has been encoded from
+fn round_trip(json: &str, decoded: pb::Block) {
+    let mainnet = Network::mainnet();
+    let block = decoded.clone().into_firehose(&mainnet).unwrap();
+    assert_eq!(block, firehose_block(json));
+    assert_eq!(
+        pb::Block::new(block.clone(), &mainnet).encode_to_vec(),
+        decoded.encode_to_vec()
+    );
+
+    let actual = decoded.arweave_json(&mainnet).unwrap();
+    assert_eq!(actual["diff"], block.diff.to_string());
+    assert_eq!(actual["weave_size"], block.weave_size.to_string());
+
+    let mut expected: Block = serde_json::from_str(json).unwrap();
+    expected.txs = vec![block.txs[0].id.clone()];
+    let mut actual: Block = serde_json::from_value(actual).unwrap();
+    // the tx tree is not part of the payload
+    expected.tx_tree = None;
+    actual.tx_tree = None;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn golden_block_100() {
+    golden("100", include_str!("../res/block_height_100.json"));
+}
+
+#[test]
+fn golden_block_269512() {
+    golden("269512", include_str!("../res/block_height_269512.json"));
+}
+
+#[test]
+fn golden_block_422250() {
+    golden("422250", include_str!("../res/block_height_422250.json"));
+}
+
+#[test]
+fn golden_synthetic_block_2_7() {
+    golden(
+        "synthetic_block_2_7",
+        include_str!("../res/synthetic_block_2_7.json"),
+    );
+}
+
+#[test]
+fn fork_fields_are_kept() {
+    let json = include_str!("../res/synthetic_block_2_7.json");
+    let block = firehose_block(json);
+    assert_eq!(
+        Block::from(&block),
+        Block {
+            txs: vec![block.txs[0].id.clone()],
+            tx_tree: Some(vec![]),
+            ..serde_json::from_str(json).unwrap()
+        }
+    );
+
+    let decoded =
+        decode(&firehose::block_line(block, &Network::mainnet(), CONFIRMS, false).unwrap());
+    assert_eq!(decoded.partition_number, 51);
+    assert_eq!(decoded.reward_key.len(), 512);
+    let info = decoded.nonce_limiter_info.unwrap();
+    assert_eq!(info.global_step_number, 15871926);
+    assert_eq!(info.last_step_checkpoints.len(), 25);
+    assert!(decoded.poa2.is_some());
+    assert!(decoded.double_signing_proof.is_none());
+    assert!(decoded.chunk2_hash.is_empty());
+
+    // older blocks leave them unset
+    let decoded = decode(
+        &firehose::block_line(
+            firehose_block(include_str!("../res/block_height_422250.json")),
+            &Network::mainnet(),
+            CONFIRMS,
+            false,
+        )
+        .unwrap(),
+    );
+    assert!(decoded.nonce_limiter_info.is_none() && decoded.reward.is_none());
+}
+
+#[test]
+fn tx_root_is_dropped_before_422250() {
+    let mut block = firehose_block(include_str!("../res/block_height_269512.json"));
+    block.tx_root = Some(
+        "lsoo-p3Tj7oblZ-54WVPHoVguqgw5rA9Jf3lLH6H8zY"
+            .parse()
+            .unwrap(),
+    );
+    let line = firehose::block_line(block, &Network::mainnet(), CONFIRMS, false).unwrap();
+    assert!(decode(&line).tx_root.is_empty());
+
+    // roots are carried as their base64url text
+    let block = firehose_block(include_str!("../res/block_height_422250.json"));
+    let line = firehose::block_line(block, &Network::mainnet(), CONFIRMS, false).unwrap();
+    assert_eq!(
+        decode(&line).tx_root,
+        b"lsoo-p3Tj7oblZ-54WVPHoVguqgw5rA9Jf3lLH6H8zY"
+    );
+}
+
+/// JSON lines printed by `thegarii decode` of `res/golden/{name}` with `args`
+fn decode_command(name: &str, args: &[&str]) -> Vec<serde_json::Value> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("res/golden")
+        .join(name);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_thegarii"))
+        .arg("decode")
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "decode failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn decode_prints_block_roots() {
+    const TX_ROOT: &str = "lsoo-p3Tj7oblZ-54WVPHoVguqgw5rA9Jf3lLH6H8zY";
+    const HASH_LIST_MERKLE: &str =
+        "akSjDrBKPuepJMOhO_S9C-iFp5zn9Glv57HGdN_WPqEToWC0Ukb37Gzs4PDA7oLU";
+
+    let blocks = decode_command("422250.fire", &[]);
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0]["tx_root"], TX_ROOT);
+    assert_eq!(blocks[0]["hash_list_merkle"], HASH_LIST_MERKLE);
+
+    let blocks = decode_command("422250.fire", &["--hex"]);
+    assert_eq!(
+        blocks[0]["tx_root"],
+        hex::encode(base64_url::decode(TX_ROOT).unwrap())
+    );
+    assert_eq!(
+        blocks[0]["hash_list_merkle"],
+        hex::encode(base64_url::decode(HASH_LIST_MERKLE).unwrap())
+    );
+}
@@ -0,0 +1,178 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! the mirror serving a block database of fixtures, its responses must parse
+//! back into the stored blocks and transactions
+#![cfg(feature = "full")]
+
+use reqwest::StatusCode;
+use std::{net::TcpListener, path::Path, process::Stdio, time::Duration};
+use thegarii::{
+    storage::Storage,
+    types::{Block, FirehoseBlock, Transaction},
+};
+use tokio::process::{Child, Command};
+
+/// firehose block of fixture `json` with `tx` as its transaction
+fn firehose_block(json: &str, tx: &str) -> FirehoseBlock {
+    let mut block: FirehoseBlock = serde_json::from_str::<Block>(json).unwrap().into();
+    block.txs = vec![serde_json::from_str(tx).unwrap()];
+    block
+}
+
+/// store `blocks` in the database at `path`, indexing their txs if `index`
+fn populate(path: &Path, blocks: &[FirehoseBlock], index: bool) {
+    let _ = std::fs::remove_dir_all(path);
+    let storage = Storage::open(path).unwrap();
+    for block in blocks {
+        storage.put_block(block).unwrap();
+        if index {
+            storage.index_txs(block).unwrap();
+        }
+    }
+    storage.flush().unwrap();
+}
+
+/// spawn the mirror of the database at `path` on a free port
+fn mirror(path: &Path) -> (Child, String) {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_thegarii"))
+        .args(["--network", "local"])
+        .args(["mirror", "--addr", &addr.to_string()])
+        .env("DB_PATH", path)
+        .env("RUST_LOG", "warn")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    (child, format!("http://{}", addr))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_stored_blocks_and_txs() {
+    let path = std::env::temp_dir().join(format!("thegarii-mirror-{}", std::process::id()));
+    let blocks = [
+        firehose_block(
+            include_str!("../res/block_height_100.json"),
+            include_str!("../res/tx.json"),
+        ),
+        firehose_block(
+            include_str!("../res/block_height_269512.json"),
+            include_str!("../res/tx_format_2.json"),
+        ),
+    ];
+    populate(&path, &blocks, true);
+
+    let (_child, endpoint) = mirror(&path);
+    let http = reqwest::Client::new();
+    let get = |route: String| {
+        let request = http.get(format!("{}/{}", endpoint, route));
+        async move {
+            let response = request.send().await.unwrap();
+            (response.status(), response.text().await.unwrap())
+        }
+    };
+
+    // wait for the mirror to listen
+    let mut ready = false;
+    for _ in 0..100 {
+        if http.get(format!("{}/info", endpoint)).send().await.is_ok() {
+            ready = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(ready, "mirror did not start");
+
+    for block in &blocks {
+        for route in [
+            format!("block/height/{}", block.height),
+            format!("block/hash/{}", block.indep_hash),
+        ] {
+            let (status, body) = get(route).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(
+                serde_json::from_str::<Block>(&body).unwrap(),
+                Block::from(block)
+            );
+        }
+
+        let tx = &block.txs[0];
+        let (status, body) = get(format!("tx/{}", tx.id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_str::<Transaction>(&body).unwrap(),
+            tx.as_returned()
+        );
+    }
+
+    let tx = &blocks[0].txs[0];
+    assert_eq!(
+        get(format!("tx/{}/data", tx.id)).await,
+        (StatusCode::OK, tx.data.to_string())
+    );
+
+    let (status, body) = get("current_block".into()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Block>(&body).unwrap().height, 269512);
+    let (_, info) = get("info".into()).await;
+    let info: serde_json::Value = serde_json::from_str(&info).unwrap();
+    assert_eq!(
+        (info["height"].as_u64(), info["blocks"].as_u64()),
+        (Some(269512), Some(2))
+    );
+    assert_eq!(info["network"], "local");
+
+    for route in [
+        "block/height/101",
+        "block/hash/unknown",
+        "tx/unknown",
+        "tx/unknown/data",
+        "unknown",
+    ] {
+        assert_eq!(
+            get(route.into()).await.0,
+            StatusCode::NOT_FOUND,
+            "{}",
+            route
+        );
+    }
+    // format 2 payloads are not stored
+    let tx = &blocks[1].txs[0];
+    assert_eq!(
+        get(format!("tx/{}/data", tx.id)).await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        get("block/height/abc".into()).await.0,
+        StatusCode::BAD_REQUEST
+    );
+
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refuses_unindexed_txs() {
+    let path =
+        std::env::temp_dir().join(format!("thegarii-mirror-unindexed-{}", std::process::id()));
+    let block = firehose_block(
+        include_str!("../res/block_height_100.json"),
+        include_str!("../res/tx.json"),
+    );
+    populate(&path, &[block], false);
+
+    let (child, _) = mirror(&path);
+    let output = tokio::time::timeout(Duration::from_secs(30), child.wait_with_output())
+        .await
+        .expect("mirror did not exit")
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not indexed"));
+
+    let _ = std::fs::remove_dir_all(&path);
+}
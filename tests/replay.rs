@@ -0,0 +1,94 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! `thegarii console --replay` of archives exported from a simulated
+//! arweave node, the FIRE stream must be the one of live polling
+mod cli;
+mod simulator;
+
+use cli::{check_stream, poll, run, scratch};
+use simulator::{Faults, Simulator};
+use std::time::Duration;
+use thegarii::{
+    archive::{self, Compression, Format},
+    network::Network,
+    types::Block,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replays_archives_like_live_polling() {
+    let simulator = Simulator::start(20, Duration::from_secs(3600), Faults::default()).await;
+    let live = poll(&simulator, "replay", 12).await;
+    check_stream(&simulator, &live, 12);
+
+    let dir = scratch("replay");
+    let (archive, json) = (dir.join("archive"), dir.join("json"));
+    let export = [
+        "export",
+        "--start",
+        "0",
+        "--end",
+        "12",
+        "--out",
+        &archive.to_string_lossy(),
+        "--format",
+        "pb-delimited",
+        "--blocks-per-file",
+        "5",
+        "--compression",
+        "gzip",
+    ];
+    let output = run(&[simulator.endpoint()], &export).await;
+    assert!(
+        output.status.success(),
+        "export failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // the same blocks in the shape of `res/`
+    std::fs::create_dir_all(json.join("tx")).unwrap();
+    for file in archive::files(&archive, Format::PbDelimited, Compression::Gzip).unwrap() {
+        for block in archive::blocks(
+            &file,
+            Format::PbDelimited,
+            Compression::Gzip,
+            Network::mainnet(),
+        )
+        .unwrap()
+        {
+            let block = block.unwrap();
+            for tx in &block.txs {
+                let path = json.join("tx").join(format!("{}.json", tx.id));
+                std::fs::write(path, serde_json::to_vec(tx).unwrap()).unwrap();
+            }
+            let path = json.join(format!("block_height_{}.json", block.height));
+            std::fs::write(path, serde_json::to_vec(&Block::from(&block)).unwrap()).unwrap();
+        }
+    }
+
+    // nothing listens on the endpoint, replays need no network
+    for source in [archive, json] {
+        let state = dir.join("state").join(source.file_name().unwrap());
+        let replay = [
+            "console",
+            "--data-directory",
+            &state.to_string_lossy(),
+            "--start",
+            "0",
+            "--replay",
+            &format!(
+                "{}:{}",
+                source.file_name().unwrap().to_string_lossy(),
+                source.display()
+            ),
+        ];
+        let output = run(&["http://127.0.0.1:9".to_string()], &replay).await;
+        assert!(
+            output.status.success(),
+            "replay failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), live);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
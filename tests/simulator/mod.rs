@@ -0,0 +1,347 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! simulated arweave node for integration tests
+//!
+//! the chain advances on a timer and every block and transaction is derived
+//! from its height and the fork it belongs to, so the canonical chain can be
+//! checked against the emitted firehose logs at any time. faults are
+//! injected per request wrt `Faults`.
+#![allow(dead_code)]
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use rand::Rng;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// faults injected into responses, probabilities are per request
+#[derive(Clone, Debug, Default)]
+pub struct Faults {
+    /// random latency up to this duration
+    pub latency: Duration,
+    /// probability of `429 Too Many Requests`
+    pub throttle: f64,
+    /// probability of `500`, `502` or `503`
+    pub server_error: f64,
+    /// probability of a `200` with half of the body
+    pub truncate: f64,
+    /// probability of `/current_block` returning a block behind the head
+    pub stale_head: f64,
+    /// how far behind the head stale heads are
+    pub stale_depth: u64,
+    /// probability of `/tx/{id}` returning `404`
+    pub missing_tx: f64,
+    /// probability of `/tx/{id}` returning another transaction
+    pub forged_tx: f64,
+    /// replace the last `depth` blocks every `every` new blocks
+    pub reorg: Option<Reorg>,
+}
+
+/// reorg of the last `depth` blocks every `every` new blocks
+#[derive(Clone, Copy, Debug)]
+pub struct Reorg {
+    pub every: u64,
+    pub depth: u64,
+}
+
+/// counters of injected faults
+#[derive(Clone, Debug, Default)]
+pub struct Injected {
+    pub throttled: u64,
+    pub server_errors: u64,
+    pub truncated: u64,
+    pub stale_heads: u64,
+    pub missing_txs: u64,
+    pub forged_txs: u64,
+    pub reorgs: u64,
+}
+
+#[derive(Debug, Default)]
+struct Chain {
+    head: u64,
+    /// fork of each height, 0 if absent
+    forks: HashMap<u64, u32>,
+    injected: Injected,
+}
+
+impl Chain {
+    fn fork(&self, height: u64) -> u32 {
+        self.forks.get(&height).copied().unwrap_or_default()
+    }
+}
+
+/// base64url of sha256 of `parts`
+fn digest(parts: &[&str]) -> String {
+    base64_url::encode(&Sha256::digest(parts.join("/")))
+}
+
+/// simulated arweave node
+pub struct Simulator {
+    pub addr: SocketAddr,
+    chain: Arc<Mutex<Chain>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Simulator {
+    /// start a node at `head` producing a block every `block_time`
+    pub async fn start(head: u64, block_time: Duration, faults: Faults) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let chain = Arc::new(Mutex::new(Chain {
+            head,
+            ..Default::default()
+        }));
+
+        let producer = {
+            let chain = chain.clone();
+            let reorg = faults.reorg;
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(block_time).await;
+                    let mut chain = chain.lock().unwrap();
+                    chain.head += 1;
+                    if let Some(reorg) = reorg {
+                        if chain.head % reorg.every == 0 {
+                            for height in chain.head.saturating_sub(reorg.depth - 1)..=chain.head {
+                                *chain.forks.entry(height).or_default() += 1;
+                            }
+                            chain.injected.reorgs += 1;
+                        }
+                    }
+                }
+            })
+        };
+
+        let server = {
+            let chain = chain.clone();
+            let faults = Arc::new(faults);
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let chain = chain.clone();
+                    let faults = faults.clone();
+                    tokio::spawn(async move {
+                        let service = service_fn(|request: Request<Incoming>| {
+                            let chain = chain.clone();
+                            let faults = faults.clone();
+                            async move {
+                                Ok::<_, Infallible>(
+                                    respond(&chain, &faults, request.uri().path()).await,
+                                )
+                            }
+                        });
+                        let _ = http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            })
+        };
+
+        Self {
+            addr,
+            chain,
+            tasks: vec![producer, server],
+        }
+    }
+
+    /// endpoint of this node
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// current head
+    pub fn head(&self) -> u64 {
+        self.chain.lock().unwrap().head
+    }
+
+    /// faults injected so far
+    pub fn injected(&self) -> Injected {
+        self.chain.lock().unwrap().injected.clone()
+    }
+
+    /// canonical `indep_hash` at `height`
+    pub fn indep_hash(&self, height: u64) -> String {
+        let chain = self.chain.lock().unwrap();
+        indep_hash(&chain, height)
+    }
+
+    /// canonical tx ids at `height`
+    pub fn tx_ids(&self, height: u64) -> Vec<String> {
+        let chain = self.chain.lock().unwrap();
+        tx_ids(&chain, height)
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        self.tasks.iter().for_each(JoinHandle::abort);
+    }
+}
+
+fn indep_hash(chain: &Chain, height: u64) -> String {
+    digest(&[
+        "block",
+        &height.to_string(),
+        &chain.fork(height).to_string(),
+    ])
+}
+
+/// blocks at even heights carry two txs, odd ones none
+fn tx_ids(chain: &Chain, height: u64) -> Vec<String> {
+    if height % 2 == 1 {
+        return vec![];
+    }
+
+    (0..2)
+        .map(|i| {
+            digest(&[
+                "tx",
+                &height.to_string(),
+                &chain.fork(height).to_string(),
+                &i.to_string(),
+            ])
+        })
+        .collect()
+}
+
+fn block(chain: &Chain, height: u64) -> Value {
+    json!({
+        "nonce": digest(&["nonce", &height.to_string()]),
+        "previous_block": if height == 0 { String::new() } else { indep_hash(chain, height - 1) },
+        "timestamp": 1_600_000_000 + height * 120,
+        "last_retarget": 1_600_000_000,
+        "diff": "1000",
+        "height": height,
+        "hash": digest(&["hash", &height.to_string()]),
+        "indep_hash": indep_hash(chain, height),
+        "txs": tx_ids(chain, height),
+        "wallet_list": digest(&["wallet_list", &height.to_string()]),
+        "reward_addr": "unclaimed",
+        "tags": [],
+        "reward_pool": 10,
+        "weave_size": 100 * height,
+        "block_size": 10,
+        "cumulative_diff": "5",
+        "hash_list_merkle": digest(&["hash_list_merkle", &height.to_string()]),
+    })
+}
+
+fn tx(id: &str) -> Value {
+    json!({
+        "format": 2,
+        "id": id,
+        "last_tx": "",
+        "owner": digest(&["owner", id]),
+        "tags": [{ "name": base64_url::encode("App-Name"), "value": base64_url::encode("simulator") }],
+        "target": "",
+        "quantity": "0",
+        "data_root": "",
+        "data": "",
+        "data_size": "0",
+        "reward": "42",
+        "signature": digest(&["signature", id]),
+    })
+}
+
+fn response(status: StatusCode, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response
+}
+
+async fn respond(chain: &Mutex<Chain>, faults: &Faults, path: &str) -> Response<Full<Bytes>> {
+    let (latency, roll, fault) = {
+        let mut rng = rand::thread_rng();
+        let latency = faults.latency.mul_f64(rng.gen::<f64>());
+        (latency, rng.gen::<f64>(), rng.gen::<f64>())
+    };
+    tokio::time::sleep(latency).await;
+
+    let mut chain = chain.lock().unwrap();
+    if roll < faults.throttle {
+        chain.injected.throttled += 1;
+        return response(StatusCode::TOO_MANY_REQUESTS, "Too Many Requests");
+    }
+    if roll < faults.throttle + faults.server_error {
+        chain.injected.server_errors += 1;
+        let status = [
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ][(fault * 3.0) as usize % 3];
+        return response(status, "");
+    }
+
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    let body = match segments.as_slice() {
+        ["current_block"] => {
+            let mut head = chain.head;
+            if fault < faults.stale_head {
+                chain.injected.stale_heads += 1;
+                head = head.saturating_sub(faults.stale_depth);
+            }
+            block(&chain, head)
+        }
+        ["block", "height", height] => match height.parse::<u64>() {
+            Ok(height) if height <= chain.head => block(&chain, height),
+            _ => return response(StatusCode::NOT_FOUND, "Block not found."),
+        },
+        ["block", "hash", hash] => {
+            match (0..=chain.head).find(|h| indep_hash(&chain, *h) == *hash) {
+                Some(height) => block(&chain, height),
+                None => return response(StatusCode::NOT_FOUND, "Block not found."),
+            }
+        }
+        ["tx", id, "status"] => {
+            match (0..=chain.head).find(|h| tx_ids(&chain, *h).iter().any(|t| t == id)) {
+                Some(height) => json!({
+                    "block_height": height,
+                    "block_indep_hash": indep_hash(&chain, height),
+                    "number_of_confirmations": chain.head - height + 1,
+                }),
+                None => return response(StatusCode::NOT_FOUND, "Not Found."),
+            }
+        }
+        ["tx", id] => {
+            let known = (0..=chain.head)
+                .rev()
+                .any(|h| tx_ids(&chain, h).iter().any(|t| t == id));
+            if !known {
+                return response(StatusCode::NOT_FOUND, "Not Found.");
+            }
+            if fault < faults.missing_tx {
+                chain.injected.missing_txs += 1;
+                return response(StatusCode::NOT_FOUND, "Not Found.");
+            }
+            if fault < faults.missing_tx + faults.forged_tx {
+                chain.injected.forged_txs += 1;
+                return response(StatusCode::OK, serde_json::to_vec(&tx("Zm9yZ2Vk")).unwrap());
+            }
+            tx(id)
+        }
+        _ => return response(StatusCode::NOT_FOUND, "Not Found."),
+    };
+
+    let body = serde_json::to_vec(&body).unwrap();
+    if roll < faults.throttle + faults.server_error + faults.truncate {
+        chain.injected.truncated += 1;
+        return response(StatusCode::OK, body[..body.len() / 2].to_vec());
+    }
+
+    response(StatusCode::OK, body)
+}
@@ -0,0 +1,137 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Merkle trees of arweave, checked offline against the proof of access of
+//! a mainnet block, and `tx_root` of mainnet blocks recomputed from their
+//! transactions, fetched from arweave.net, run with
+//! `cargo test --test tx_root -- --ignored`
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use thegarii::{
+    network::{Fork, Network},
+    types::{Base64Url, Block},
+    verify::{self, TxRootPolicy},
+    Client,
+};
+
+/// bytes of a chunk of transaction data
+const DATA_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// mainnet client failing blocks whose txs do not hash to their tx_root
+fn client() -> Client {
+    Client::new(
+        vec!["https://arweave.net".into()],
+        Duration::from_secs(60),
+        3,
+        Network::mainnet(),
+    )
+    .unwrap()
+    .with_tx_root(TxRootPolicy::Fail)
+}
+
+#[test]
+fn proof_of_access_of_block_422250() {
+    let block: Block =
+        serde_json::from_str(include_str!("../res/block_height_422250.json")).unwrap();
+    let poa = block.poa.unwrap();
+    let (tx_path, data_path, chunk) = (
+        poa.tx_path.to_vec(),
+        poa.data_path.to_vec(),
+        poa.chunk.to_vec(),
+    );
+
+    // the tx_root of the recall block is the id of the top node of tx_path
+    let tx_root = Sha256::new()
+        .chain_update(Sha256::digest(&tx_path[..32]))
+        .chain_update(Sha256::digest(&tx_path[32..64]))
+        .chain_update(Sha256::digest(&tx_path[64..96]))
+        .finalize();
+    assert_eq!(
+        Base64Url::from(tx_root.to_vec()).to_string(),
+        "XAmAsIwRKcMXjveN-1VqK7tCj9QQBgmmMT74GWM9mTQ"
+    );
+
+    // recalled tx, ending at offset 10026897 of the recall block
+    let (data_root, end) = verify::validate_path(&tx_root, 10026896, &tx_path).unwrap();
+    assert_eq!(end, 10026897);
+    assert_eq!(
+        Base64Url::from(data_root.clone()).to_string(),
+        "xQguhfH8daMBAQrveQq3MMp4iKB3khk5mbU34Ckl1q8"
+    );
+    assert_eq!(verify::validate_path(&tx_root, 0, &tx_path), None);
+
+    // recalled chunk, the last one of the tx
+    let (chunk_hash, end) = verify::validate_path(&data_root, 786432, &data_path).unwrap();
+    assert_eq!(end, 1048576);
+    assert_eq!(chunk_hash, Sha256::digest(&chunk).to_vec());
+    // and the right child of the last node of data_path
+    assert_eq!(
+        verify::merkle_root([(chunk_hash, end)]),
+        data_path[data_path.len() - 128..data_path.len() - 96]
+    );
+}
+
+#[tokio::test]
+#[ignore = "fetches blocks from arweave.net"]
+async fn tx_root_of_block_422250() {
+    let mainnet = Network::mainnet();
+    let block = client().get_firehose_block_by_height(422250).await.unwrap();
+    assert_eq!(
+        block
+            .txs
+            .iter()
+            .map(|tx| tx.id.to_string())
+            .collect::<Vec<_>>(),
+        ["IRPCjc_ws7aS5GWp4mwR2k-HuQy-zT_GWrgR6kRdbmI"]
+    );
+    assert_eq!(
+        Base64Url::from(verify::tx_root(&block.txs, block.height, &mainnet)).to_string(),
+        "lsoo-p3Tj7oblZ-54WVPHoVguqgw5rA9Jf3lLH6H8zY"
+    );
+}
+
+#[tokio::test]
+#[ignore = "fetches blocks from arweave.net"]
+async fn tx_root_of_padded_block() {
+    let mainnet = Network::mainnet();
+    let mut unpadded = mainnet.clone();
+    unpadded.forks.remove(&Fork::V2_5);
+
+    // first block since 2.5 with data not aligned to chunks
+    let client = client();
+    let start = mainnet.height(Fork::V2_5).unwrap();
+    for height in start..start + 100 {
+        if client
+            .get_block_by_height(height)
+            .await
+            .unwrap()
+            .txs
+            .is_empty()
+        {
+            continue;
+        }
+
+        let block = client.get_firehose_block_by_height(height).await.unwrap();
+        let padded = block.txs.iter().any(|tx| {
+            tx.data_size
+                .to_u64()
+                .is_some_and(|size| size % DATA_CHUNK_SIZE != 0)
+        });
+        if !padded {
+            continue;
+        }
+
+        let tx_root = block.tx_root.clone().unwrap();
+        assert_eq!(
+            Base64Url::from(verify::tx_root(&block.txs, height, &mainnet)),
+            tx_root
+        );
+        assert_ne!(
+            Base64Url::from(verify::tx_root(&block.txs, height, &unpadded)),
+            tx_root
+        );
+        return;
+    }
+
+    panic!("no block with padded txs in {}..{}", start, start + 100);
+}
@@ -0,0 +1,50 @@
+// Copyright 2021 ChainSafe Systems
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! `thegarii verify` auditing simulated arweave nodes
+mod cli;
+mod simulator;
+
+use cli::run;
+use simulator::{Faults, Simulator};
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_reports_divergent_endpoints() {
+    let healthy = Simulator::start(8, Duration::from_secs(3600), Faults::default()).await;
+    let forging = Simulator::start(
+        8,
+        Duration::from_secs(3600),
+        Faults {
+            forged_tx: 1.0,
+            ..Default::default()
+        },
+    )
+    .await;
+    let args = ["verify", "--start", "1", "--end", "8"];
+
+    let output = run(&[healthy.endpoint(), forging.endpoint()], &args).await;
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = run(
+        &[healthy.endpoint(), forging.endpoint()],
+        &[&args[..], &["--tx-root"]].concat(),
+    )
+    .await;
+    assert!(!output.status.success());
+    let findings = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    // blocks at even heights carry txs
+    assert_eq!(
+        findings
+            .iter()
+            .map(|f| (f["kind"].as_str().unwrap(), f["height"].as_u64().unwrap()))
+            .collect::<Vec<_>>(),
+        [2, 4, 6, 8].map(|height| ("tx_root_mismatch", height))
+    );
+    assert!(findings.iter().all(|f| f["endpoint"] == forging.endpoint()));
+}